@@ -0,0 +1,282 @@
+use std::collections::{BTreeMap, VecDeque};
+use swf_types as swf;
+
+// The character ID defined by a tag, if any.
+fn defined_id(tag: &swf::Tag) -> Option<u16> {
+    Some(match tag {
+        swf::Tag::DefineBinaryData(def) => def.id,
+        swf::Tag::DefineBitmap(def) => def.id,
+        swf::Tag::DefineButton(def) => def.id,
+        swf::Tag::DefineCffFont(def) => def.id,
+        swf::Tag::DefineDynamicText(def) => def.id,
+        swf::Tag::DefineFont(def) => def.id,
+        swf::Tag::DefineGlyphFont(def) => def.id,
+        swf::Tag::DefineMorphShape(def) => def.id,
+        swf::Tag::DefineShape(def) => def.id,
+        swf::Tag::DefineSound(def) => def.id,
+        swf::Tag::DefineSprite(def) => def.id,
+        swf::Tag::DefineText(def) => def.id,
+        swf::Tag::DefineVideoStream(def) => def.id,
+        _ => return None,
+    })
+}
+
+// The character ID a tag adds information to, for tags which
+// have to be copied along with the definition of that character.
+fn attached_id(tag: &swf::Tag) -> Option<u16> {
+    Some(match tag {
+        swf::Tag::DefineButtonColorTransform(tag) => tag.button_id,
+        swf::Tag::DefineButtonSound(tag) => tag.button_id,
+        swf::Tag::DefineFontAlignZones(tag) => tag.font_id,
+        swf::Tag::DefineFontInfo(tag) => tag.font_id,
+        swf::Tag::DefineFontName(tag) => tag.font_id,
+        swf::Tag::DefineScalingGrid(tag) => tag.character_id,
+        swf::Tag::DoInitAction(tag) => tag.sprite_id,
+        _ => return None,
+    })
+}
+
+fn visit_fill_ids(fill: &mut swf::FillStyle, f: &mut impl FnMut(&mut u16)) {
+    if let swf::FillStyle::Bitmap(bitmap) = fill {
+        f(&mut bitmap.bitmap_id);
+    }
+}
+
+fn visit_style_ids(styles: &mut swf::ShapeStyles, f: &mut impl FnMut(&mut u16)) {
+    for fill in &mut styles.fill {
+        visit_fill_ids(fill, f);
+    }
+    for line in &mut styles.line {
+        visit_fill_ids(&mut line.fill, f);
+    }
+}
+
+// Call `f` on every character ID in a tag, be it defined or referenced.
+fn visit_ids(tag: &mut swf::Tag, f: &mut impl FnMut(&mut u16)) {
+    match tag {
+        swf::Tag::DefineShape(def) => {
+            f(&mut def.id);
+            visit_style_ids(&mut def.shape.initial_styles, f);
+            for record in &mut def.shape.records {
+                if let swf::ShapeRecord::StyleChange(change) = record {
+                    if let Some(styles) = &mut change.new_styles {
+                        visit_style_ids(styles, f);
+                    }
+                }
+            }
+        }
+        swf::Tag::DefineSprite(def) => {
+            f(&mut def.id);
+            for tag in &mut def.tags {
+                match tag {
+                    swf::Tag::PlaceObject(place) => {
+                        if let Some(id) = &mut place.character_id {
+                            f(id);
+                        }
+                    }
+                    swf::Tag::RemoveObject(remove) => {
+                        if let Some(id) = &mut remove.character_id {
+                            f(id);
+                        }
+                    }
+                    swf::Tag::StartSound(sound) => f(&mut sound.sound_id),
+                    _ => {}
+                }
+            }
+        }
+        swf::Tag::DefineButton(def) => {
+            f(&mut def.id);
+            for record in &mut def.characters {
+                f(&mut record.character_id);
+            }
+        }
+        swf::Tag::DefineButtonSound(tag) => {
+            f(&mut tag.button_id);
+            let sounds = tag
+                .over_up_to_idle
+                .iter_mut()
+                .chain(&mut tag.idle_to_over_up)
+                .chain(&mut tag.over_up_to_over_down)
+                .chain(&mut tag.over_down_to_over_up);
+            for sound in sounds {
+                f(&mut sound.sound_id);
+            }
+        }
+        swf::Tag::DefineText(def) => {
+            f(&mut def.id);
+            for record in &mut def.records {
+                if let Some(id) = &mut record.font_id {
+                    f(id);
+                }
+            }
+        }
+        swf::Tag::DefineDynamicText(def) => {
+            f(&mut def.id);
+            if let Some(id) = &mut def.font_id {
+                f(id);
+            }
+        }
+        swf::Tag::DefineBinaryData(def) => f(&mut def.id),
+        swf::Tag::DefineBitmap(def) => f(&mut def.id),
+        swf::Tag::DefineCffFont(def) => f(&mut def.id),
+        swf::Tag::DefineFont(def) => f(&mut def.id),
+        swf::Tag::DefineGlyphFont(def) => f(&mut def.id),
+        // FIXME handle bitmap fills in morph shapes.
+        swf::Tag::DefineMorphShape(def) => f(&mut def.id),
+        swf::Tag::DefineSound(def) => f(&mut def.id),
+        swf::Tag::DefineVideoStream(def) => f(&mut def.id),
+        swf::Tag::DefineButtonColorTransform(tag) => f(&mut tag.button_id),
+        swf::Tag::DefineFontAlignZones(tag) => f(&mut tag.font_id),
+        swf::Tag::DefineFontInfo(tag) => f(&mut tag.font_id),
+        swf::Tag::DefineFontName(tag) => f(&mut tag.font_id),
+        swf::Tag::DefineScalingGrid(tag) => f(&mut tag.character_id),
+        swf::Tag::DoInitAction(tag) => f(&mut tag.sprite_id),
+        _ => {}
+    }
+}
+
+// The names a movie exports its characters under, via `ExportAssets`.
+pub fn export_names(movie: &swf::Movie) -> BTreeMap<&str, u16> {
+    let mut names = BTreeMap::new();
+    for tag in &movie.tags {
+        if let swf::Tag::ExportAssets(export) = tag {
+            for asset in &export.assets {
+                names.insert(&asset.name[..], asset.id);
+            }
+        }
+    }
+    names
+}
+
+// Copy the characters requested by `import` out of `library`, along with
+// everything they depend on. Imported characters take the IDs chosen by
+// the importing movie, while their dependencies get new IDs, allocated
+// starting at `next_id`, to avoid conflicts with the importing movie.
+// NB: `next_id` is never allocated if it's the last ID (65535), so it
+// can stay there once IDs run out, and nothing else gets imported.
+pub fn import(
+    library: &swf::Movie,
+    import: &swf::tags::ImportAssets,
+    next_id: &mut u16,
+) -> Vec<swf::Tag> {
+    let mut definitions = BTreeMap::new();
+    let mut attached = BTreeMap::<u16, Vec<&swf::Tag>>::new();
+    for tag in &library.tags {
+        if let Some(id) = defined_id(tag) {
+            definitions.insert(id, tag);
+        } else if let Some(id) = attached_id(tag) {
+            attached.entry(id).or_default().push(tag);
+        }
+    }
+    let names = export_names(library);
+
+    // Maps IDs in `library` to IDs in the importing movie.
+    let mut ids = BTreeMap::new();
+    let mut queue = VecDeque::new();
+    for asset in &import.assets {
+        match names.get(&asset.name[..]) {
+            Some(&id) => {
+                ids.insert(id, asset.id);
+                queue.push_back(id);
+            }
+            None => eprintln!(
+                "assets::import: {:?} doesn't export {:?}",
+                import.url, asset.name
+            ),
+        }
+    }
+
+    let mut tags = vec![];
+    let mut out_of_ids = false;
+    while let Some(id) = queue.pop_front() {
+        let definition = match definitions.get(&id) {
+            Some(&tag) => tag,
+            None => {
+                eprintln!("assets::import: {:?} doesn't define ID {}", import.url, id);
+                continue;
+            }
+        };
+        let attached = attached.get(&id).into_iter().flatten().cloned();
+        for tag in Some(definition).into_iter().chain(attached) {
+            let mut tag = tag.clone();
+            visit_ids(&mut tag, &mut |id| {
+                if let Some(&new_id) = ids.get(id) {
+                    *id = new_id;
+                    return;
+                }
+                match next_id.checked_add(1) {
+                    Some(after) => {
+                        ids.insert(*id, *next_id);
+                        queue.push_back(*id);
+                        *id = *next_id;
+                        *next_id = after;
+                    }
+                    None => out_of_ids = true,
+                }
+            });
+            if out_of_ids {
+                eprintln!(
+                    "assets::import: ran out of character IDs, importing from {:?}",
+                    import.url
+                );
+                return tags;
+            }
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+// Resolve all of the `ImportAssets` in `movie`, using `load` to find the
+// movie each URL refers to, and return the imported definition tags.
+// Libraries can import characters themselves, which are resolved first
+// (with their URLs also passed to `load`, as if `movie` had them).
+pub fn resolve_imports(
+    movie: &swf::Movie,
+    load: impl Fn(&str) -> Option<swf::Movie>,
+) -> Vec<swf::Tag> {
+    resolve_imports_from(movie, &load, &mut vec![])
+}
+
+// Like `resolve_imports`, but `loading` has the URLs of the libraries
+// being resolved (which `movie` is imported into), to stop cycles.
+fn resolve_imports_from(
+    movie: &swf::Movie,
+    load: &dyn Fn(&str) -> Option<swf::Movie>,
+    loading: &mut Vec<String>,
+) -> Vec<swf::Tag> {
+    // NB: see `import` for how this is kept below the last ID.
+    let mut next_id = movie
+        .tags
+        .iter()
+        .filter_map(|tag| match tag {
+            swf::Tag::ImportAssets(import) => import.assets.iter().map(|asset| asset.id).max(),
+            _ => defined_id(tag),
+        })
+        .max()
+        .map_or(1, |id| id.saturating_add(1));
+
+    let mut tags = vec![];
+    for tag in &movie.tags {
+        if let swf::Tag::ImportAssets(import) = tag {
+            if loading.contains(&import.url) {
+                eprintln!(
+                    "assets::resolve_imports: {:?} imports from itself (via {:?})",
+                    import.url, loading
+                );
+                continue;
+            }
+            match load(&import.url) {
+                Some(mut library) => {
+                    loading.push(import.url.clone());
+                    let imported = resolve_imports_from(&library, load, loading);
+                    loading.pop();
+                    library.tags.splice(0..0, imported);
+                    tags.extend(self::import(&library, import, &mut next_id));
+                }
+                None => eprintln!("assets::resolve_imports: couldn't load {:?}", import.url),
+            }
+        }
+    }
+    tags
+}
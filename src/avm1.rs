@@ -1,4 +1,8 @@
 use crate::timeline::Frame;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::iter;
+use std::mem;
 
 #[derive(Clone, Debug)]
 pub enum Value {
@@ -11,6 +15,9 @@ pub enum Value {
     Str(String),
 
     OpRes(usize),
+    // The result of an op which is only used once, evaluated in place
+    // (see `Code::optimize`), instead of being kept in a temporary.
+    Op(Box<Op>),
 }
 
 impl Value {
@@ -29,32 +36,521 @@ impl Value {
             _ => None,
         }
     }
+
+    // Numeric constants converted to integers, the way JS's `x | 0` does.
+    fn to_int32(&self) -> Option<i32> {
+        let x = match *self {
+            Value::I32(x) => return Some(x),
+            Value::F32(x) => f64::from(x),
+            Value::F64(x) => x,
+            _ => return None,
+        };
+        Some(to_int32(x))
+    }
+
+    fn is_const(&self) -> bool {
+        !matches!(self, Value::OpRes(_) | Value::Op(_))
+    }
+
+    // The JS value a constant is exported as (see `export::js::avm1`).
+    fn to_const(&self) -> Option<Const> {
+        Some(match self {
+            Value::Undefined => Const::Undefined,
+            Value::Null => Const::Null,
+            Value::Bool(b) => Const::Bool(*b),
+            Value::I32(x) => Const::Num(f64::from(*x)),
+            // NB: exported as the shortest decimal which round-trips.
+            Value::F32(x) => Const::Num(x.to_string().parse().ok()?),
+            Value::F64(x) => Const::Num(*x),
+            Value::Str(s) => Const::Str(s.clone()),
+            Value::OpRes(_) | Value::Op(_) => return None,
+        })
+    }
+}
+
+// Numbers converted to integers, the way JS's `x | 0` does.
+fn to_int32(x: f64) -> i32 {
+    if !x.is_finite() {
+        return 0;
+    }
+    (x.trunc() % 4294967296.0) as i64 as i32
+}
+
+// A constant, as a JS value, for folding operators (see `Code::optimize`).
+// The conversions below give up (returning `None`) on anything where they
+// might not match JS exactly (e.g. numbers printed with exponents).
+#[derive(Clone, Debug, PartialEq)]
+enum Const {
+    Undefined,
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+}
+
+impl Const {
+    fn into_value(self) -> Option<Value> {
+        Some(match self {
+            Const::Undefined => Value::Undefined,
+            Const::Null => Value::Null,
+            Const::Bool(b) => Value::Bool(b),
+            // `inf` isn't valid JS (and `Infinity` could be shadowed).
+            Const::Num(x) if x.is_infinite() => return None,
+            Const::Num(x)
+                if x == x.trunc()
+                    && x.abs() < 2147483648.0
+                    && !(x == 0.0 && x.is_sign_negative()) =>
+            {
+                Value::I32(x as i32)
+            }
+            Const::Num(x) => Value::F64(x),
+            Const::Str(s) => Value::Str(s),
+        })
+    }
+
+    fn to_bool(&self) -> bool {
+        match self {
+            Const::Undefined | Const::Null => false,
+            Const::Bool(b) => *b,
+            Const::Num(x) => *x != 0.0 && !x.is_nan(),
+            Const::Str(s) => !s.is_empty(),
+        }
+    }
+
+    fn to_number(&self) -> Option<f64> {
+        Some(match self {
+            Const::Undefined => f64::NAN,
+            Const::Null | Const::Bool(false) => 0.0,
+            Const::Bool(true) => 1.0,
+            Const::Num(x) => *x,
+            Const::Str(s) => {
+                if !s.is_ascii() {
+                    return None;
+                }
+                let s = s.trim_matches(|c: char| c.is_ascii_whitespace() || c == '\x0b');
+                if s.is_empty() {
+                    return Some(0.0);
+                }
+                // Leave e.g. `0x10` and `Infinity` to JS.
+                if s.bytes()
+                    .any(|b| b.is_ascii_alphabetic() && b != b'e' && b != b'E')
+                {
+                    return None;
+                }
+                s.parse().unwrap_or(f64::NAN)
+            }
+        })
+    }
+
+    fn to_string(&self) -> Option<String> {
+        Some(match self {
+            Const::Undefined => "undefined".to_string(),
+            Const::Null => "null".to_string(),
+            Const::Bool(b) => b.to_string(),
+            Const::Num(x) if x.is_nan() => "NaN".to_string(),
+            Const::Num(x) if x.is_infinite() => {
+                (if *x > 0.0 { "Infinity" } else { "-Infinity" }).to_string()
+            }
+            Const::Num(x) if *x == 0.0 => "0".to_string(),
+            // JS switches to exponents outside of this range.
+            Const::Num(x) if x.abs() >= 1e-6 && x.abs() < 1e21 => x.to_string(),
+            Const::Num(_) => return None,
+            Const::Str(s) => s.clone(),
+        })
+    }
+
+    // JS's `==`, on primitives.
+    fn loose_eq(&self, other: &Const) -> Option<bool> {
+        Some(match (self, other) {
+            (Const::Undefined, _) | (Const::Null, _) | (_, Const::Undefined) | (_, Const::Null) => {
+                matches!(self, Const::Undefined | Const::Null)
+                    && matches!(other, Const::Undefined | Const::Null)
+            }
+            (Const::Str(a), Const::Str(b)) => a == b,
+            _ => self.to_number()? == other.to_number()?,
+        })
+    }
+
+    // JS's `<`, on primitives.
+    fn less_than(&self, other: &Const) -> Option<bool> {
+        Some(match (self, other) {
+            (Const::Str(a), Const::Str(b)) => a.encode_utf16().lt(b.encode_utf16()),
+            _ => self.to_number()? < other.to_number()?,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Property {
+    X,
+    Y,
+    XScale,
+    YScale,
+    CurrentFrame,
+    TotalFrames,
+    Alpha,
+    Visible,
+    Width,
+    Height,
+    Rotation,
+    Target,
+    FramesLoaded,
+    Name,
+    DropTarget,
+    Url,
+    HighQuality,
+    FocusRect,
+    SoundBufTime,
+    Quality,
+    XMouse,
+    YMouse,
+}
+
+impl Property {
+    const ALL: [Property; 22] = [
+        Property::X,
+        Property::Y,
+        Property::XScale,
+        Property::YScale,
+        Property::CurrentFrame,
+        Property::TotalFrames,
+        Property::Alpha,
+        Property::Visible,
+        Property::Width,
+        Property::Height,
+        Property::Rotation,
+        Property::Target,
+        Property::FramesLoaded,
+        Property::Name,
+        Property::DropTarget,
+        Property::Url,
+        Property::HighQuality,
+        Property::FocusRect,
+        Property::SoundBufTime,
+        Property::Quality,
+        Property::XMouse,
+        Property::YMouse,
+    ];
+
+    // Look up a property by its `GetProperty` / `SetProperty` index.
+    pub fn from_index(i: i32) -> Option<Self> {
+        if i < 0 {
+            return None;
+        }
+        Property::ALL.get(i as usize).copied()
+    }
+
+    // The name of the property, as used with `GetMember` / `SetMember`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Property::X => "_x",
+            Property::Y => "_y",
+            Property::XScale => "_xscale",
+            Property::YScale => "_yscale",
+            Property::CurrentFrame => "_currentframe",
+            Property::TotalFrames => "_totalframes",
+            Property::Alpha => "_alpha",
+            Property::Visible => "_visible",
+            Property::Width => "_width",
+            Property::Height => "_height",
+            Property::Rotation => "_rotation",
+            Property::Target => "_target",
+            Property::FramesLoaded => "_framesloaded",
+            Property::Name => "_name",
+            Property::DropTarget => "_droptarget",
+            Property::Url => "_url",
+            Property::HighQuality => "_highquality",
+            Property::FocusRect => "_focusrect",
+            Property::SoundBufTime => "_soundbuftime",
+            Property::Quality => "_quality",
+            Property::XMouse => "_xmouse",
+            Property::YMouse => "_ymouse",
+        }
+    }
+}
+
+// How `GetUrl2` sends the variables of the current timeline along.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SendVars {
+    None,
+    Get,
+    Post,
+}
+
+impl From<avm1_tree::actions::get_url2::SendVarsMethod> for SendVars {
+    fn from(method: avm1_tree::actions::get_url2::SendVarsMethod) -> Self {
+        match method {
+            avm1_tree::actions::get_url2::SendVarsMethod::None => SendVars::None,
+            avm1_tree::actions::get_url2::SendVarsMethod::Get => SendVars::Get,
+            avm1_tree::actions::get_url2::SendVarsMethod::Post => SendVars::Post,
+        }
+    }
+}
+
+// Binary operators, which behave like their JS counterparts, after
+// converting both operands (for the SWF 4 and string versions).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    StrictEq,
+    Lt,
+    Gt,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    UShr,
+
+    // SWF 4 versions of `Add`, `Eq` and `Lt`, on numbers.
+    NumAdd,
+    NumEq,
+    NumLt,
+
+    // `StringAdd`, `StringEquals`, `StringLess` and `StringGreater`.
+    StrAdd,
+    StrEq,
+    StrLt,
+    StrGt,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UnOp {
+    Not,
+    Increment,
+    Decrement,
+    ToNumber,
+    ToString,
+}
+
+impl BinOp {
+    // Evaluate the operator on constants, the same as the exported JS would.
+    fn fold(self, a: &Value, b: &Value) -> Option<Value> {
+        let (a, b) = (a.to_const()?, b.to_const()?);
+        let num = |x: f64| Const::Num(x).into_value();
+        let int = |x: i32| Const::Num(f64::from(x)).into_value();
+        let bool = |b: bool| Some(Value::Bool(b));
+        match self {
+            BinOp::Add => match (&a, &b) {
+                (Const::Str(_), _) | (_, Const::Str(_)) => {
+                    Some(Value::Str(a.to_string()? + &b.to_string()?))
+                }
+                _ => num(a.to_number()? + b.to_number()?),
+            },
+            BinOp::Sub => num(a.to_number()? - b.to_number()?),
+            BinOp::Mul => num(a.to_number()? * b.to_number()?),
+            BinOp::Div => num(a.to_number()? / b.to_number()?),
+            BinOp::Mod => num(a.to_number()? % b.to_number()?),
+            BinOp::Eq => bool(a.loose_eq(&b)?),
+            BinOp::StrictEq => bool(a == b),
+            BinOp::Lt => bool(a.less_than(&b)?),
+            BinOp::Gt => bool(b.less_than(&a)?),
+            BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr => {
+                let (x, y) = (to_int32(a.to_number()?), to_int32(b.to_number()?));
+                int(match self {
+                    BinOp::BitAnd => x & y,
+                    BinOp::BitOr => x | y,
+                    BinOp::BitXor => x ^ y,
+                    BinOp::Shl => x.wrapping_shl(y as u32),
+                    _ => x.wrapping_shr(y as u32),
+                })
+            }
+            BinOp::UShr => {
+                let (x, y) = (to_int32(a.to_number()?), to_int32(b.to_number()?));
+                num(f64::from((x as u32).wrapping_shr(y as u32)))
+            }
+            BinOp::NumAdd => num(a.to_number()? + b.to_number()?),
+            BinOp::NumEq => bool(a.to_number()? == b.to_number()?),
+            BinOp::NumLt => bool(a.to_number()? < b.to_number()?),
+            BinOp::StrAdd => Some(Value::Str(a.to_string()? + &b.to_string()?)),
+            BinOp::StrEq => bool(a.to_string()? == b.to_string()?),
+            BinOp::StrLt => {
+                bool(Const::Str(a.to_string()?).less_than(&Const::Str(b.to_string()?))?)
+            }
+            BinOp::StrGt => {
+                bool(Const::Str(b.to_string()?).less_than(&Const::Str(a.to_string()?))?)
+            }
+        }
+    }
+}
+
+impl UnOp {
+    // Evaluate the operator on a constant, the same as the exported JS would.
+    fn fold(self, a: &Value) -> Option<Value> {
+        let a = a.to_const()?;
+        match self {
+            UnOp::Not => Some(Value::Bool(!a.to_bool())),
+            UnOp::Increment => Const::Num(a.to_number()? + 1.0).into_value(),
+            UnOp::Decrement => Const::Num(a.to_number()? - 1.0).into_value(),
+            UnOp::ToNumber => Const::Num(a.to_number()?).into_value(),
+            UnOp::ToString => Some(Value::Str(a.to_string()?)),
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Op {
     Play,
     Stop,
+    NextFrame,
+    PrevFrame,
     GotoFrame(Frame),
-    // FIXME(eddyb) can we statically resolve this?
+    // NB: `Code::resolve_labels` replaces these with `GotoFrame`
+    // wherever the target timeline is statically known.
     GotoLabel(String),
+    // Go to a frame number (1-based) or label, possibly prefixed by
+    // a target path, then play (if `true`) or stop. Frame numbers are
+    // offset by the scene bias.
+    GotoFrame2(Value, bool, Frame),
     GetUrl(String, String),
+    // Open an URL in a browser window, like `GetUrl`, but dynamically.
+    GetUrl2(Value, Value, SendVars),
+    // Load URL-encoded variables from an URL into the target movie clip.
+    LoadVariables(Value, Value, SendVars),
+    // Load a movie from an URL into the target movie clip (replacing
+    // it), with an empty URL unloading the movie clip instead.
+    LoadMovie(Value, Value, SendVars),
+
+    // Redirect the following actions to another movie clip,
+    // with an empty target path switching back to the original one.
+    SetTarget(Value),
+    StopSounds,
+    ToggleQuality,
+    Trace(Value),
+
+    // Duplicate the movie clip at a target path, giving it a new name
+    // and placing it at a depth (which is offset by 16384 already).
+    CloneSprite(Value, Value, Value),
+    RemoveSprite(Value),
 
     GetVar(String),
     SetVar(String, Value),
 
+    GetProperty(Value, Property),
+    SetProperty(Value, Property, Value),
+    GetMember(Value, Value),
+    SetMember(Value, Value, Value),
+
+    Binary(BinOp, Value, Value),
+    Unary(UnOp, Value),
+    InitArray(Vec<Value>),
+    // NB: the properties are in the order they were pushed in.
+    InitObject(Vec<(String, Value)>),
+
     Call(Value, Vec<Value>),
+    New(Value, Vec<Value>),
     // FIXME(eddyb) integrate with GetMember.
     CallMethod(Value, String, Vec<Value>),
 }
 
-#[derive(Debug)]
+impl Op {
+    // The values used by this op, in the order the JS export evaluates them.
+    pub fn values(&self) -> Vec<&Value> {
+        match self {
+            Op::Play
+            | Op::Stop
+            | Op::NextFrame
+            | Op::PrevFrame
+            | Op::GotoFrame(_)
+            | Op::GotoLabel(_)
+            | Op::GetUrl(..)
+            | Op::StopSounds
+            | Op::ToggleQuality
+            | Op::GetVar(_) => vec![],
+            Op::GotoFrame2(v, ..)
+            | Op::SetTarget(v)
+            | Op::Trace(v)
+            | Op::RemoveSprite(v)
+            | Op::SetVar(_, v)
+            | Op::GetProperty(v, _)
+            | Op::Unary(_, v) => vec![v],
+            Op::GetUrl2(a, b, _)
+            | Op::LoadVariables(a, b, _)
+            | Op::LoadMovie(a, b, _)
+            | Op::SetProperty(a, _, b)
+            | Op::GetMember(a, b)
+            | Op::Binary(_, a, b) => vec![a, b],
+            Op::CloneSprite(a, b, c) | Op::SetMember(a, b, c) => vec![a, b, c],
+            Op::InitArray(elems) => elems.iter().collect(),
+            Op::InitObject(props) => props.iter().map(|(_, v)| v).collect(),
+            Op::Call(f, args) | Op::New(f, args) | Op::CallMethod(f, _, args) => {
+                iter::once(f).chain(args).collect()
+            }
+        }
+    }
+
+    pub fn values_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            Op::Play
+            | Op::Stop
+            | Op::NextFrame
+            | Op::PrevFrame
+            | Op::GotoFrame(_)
+            | Op::GotoLabel(_)
+            | Op::GetUrl(..)
+            | Op::StopSounds
+            | Op::ToggleQuality
+            | Op::GetVar(_) => vec![],
+            Op::GotoFrame2(v, ..)
+            | Op::SetTarget(v)
+            | Op::Trace(v)
+            | Op::RemoveSprite(v)
+            | Op::SetVar(_, v)
+            | Op::GetProperty(v, _)
+            | Op::Unary(_, v) => vec![v],
+            Op::GetUrl2(a, b, _)
+            | Op::LoadVariables(a, b, _)
+            | Op::LoadMovie(a, b, _)
+            | Op::SetProperty(a, _, b)
+            | Op::GetMember(a, b)
+            | Op::Binary(_, a, b) => vec![a, b],
+            Op::CloneSprite(a, b, c) | Op::SetMember(a, b, c) => vec![a, b, c],
+            Op::InitArray(elems) => elems.iter_mut().collect(),
+            Op::InitObject(props) => props.iter_mut().map(|(_, v)| v).collect(),
+            Op::Call(f, args) | Op::New(f, args) | Op::CallMethod(f, _, args) => {
+                iter::once(f).chain(args).collect()
+            }
+        }
+    }
+
+    // Whether this op only reads state, and so can be removed when its
+    // result is unused, or evaluated in a different order to other reads.
+    // Operators are only pure on constants, as they can otherwise call
+    // `valueOf` or `toString` methods, which can have side-effects.
+    // FIXME this ignores getters (`addProperty`).
+    fn is_pure(&self) -> bool {
+        match self {
+            Op::GetVar(_)
+            | Op::GetProperty(..)
+            | Op::GetMember(..)
+            | Op::InitArray(_)
+            | Op::InitObject(_) => true,
+            Op::Binary(_, a, b) => a.is_const() && b.is_const(),
+            Op::Unary(_, a) => a.is_const(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Code {
     pub ops: Vec<Op>,
+
+    // How many `ops` there were after compiling each action, i.e. action `i`
+    // compiled to `ops[ops_after_action[i - 1]..ops_after_action[i]]`.
+    pub ops_after_action: Vec<usize>,
+
+    // The index of the action compilation gave up at (if any), and why.
+    pub bailed: Option<(usize, String)>,
 }
 
 impl Code {
-    pub fn parse_and_compile(mut data: &[u8]) -> Self {
+    pub fn parse(mut data: &[u8]) -> Vec<avm1_tree::Action> {
         let mut actions = vec![];
         while data[0] != 0 {
             let (rest, action) = avm1_parser::parse_action(data).unwrap();
@@ -62,8 +558,68 @@ impl Code {
             actions.push(action);
         }
         assert_eq!(data, [0]);
+        actions
+    }
 
-        Code::compile(actions)
+    pub fn parse_and_compile(data: &[u8]) -> Self {
+        let mut code = Code::compile(Code::parse(data));
+        code.optimize();
+        code
+    }
+
+    // Replace `GotoLabel`s targeting the timeline this code is attached to
+    // (i.e. not redirected by `SetTarget`) with `GotoFrame`s, using that
+    // timeline's `labels`, and return the labels which couldn't be found.
+    pub fn resolve_labels(&mut self, labels: &BTreeMap<String, Frame>) -> Vec<String> {
+        let mut unresolved = vec![];
+        let mut own_target = true;
+        for op in &mut self.ops {
+            match op {
+                Op::SetTarget(Value::Str(target)) => own_target = target.is_empty(),
+                Op::SetTarget(_) => own_target = false,
+                Op::GotoLabel(label) if own_target => match labels.get(&label.to_lowercase()) {
+                    Some(&frame) => *op = Op::GotoFrame(frame),
+                    None => unresolved.push(label.clone()),
+                },
+                _ => {}
+            }
+        }
+        unresolved
+    }
+
+    // Make frame numbers relative to `start`, for frames in `start..end`
+    // (e.g. a scene exported on its own), removing (and returning) any
+    // `GotoFrame`s to other frames, which don't exist anymore.
+    pub fn rebase_frames(&mut self, start: Frame, end: Frame) -> Vec<Frame> {
+        let mut outside = vec![];
+        let mut own_target = true;
+        let mut new_index = vec![];
+        let mut ops_before = vec![0];
+        for op in mem::take(&mut self.ops) {
+            let mut op = Some(op);
+            match &mut op {
+                Some(Op::SetTarget(Value::Str(target))) => own_target = target.is_empty(),
+                Some(Op::SetTarget(_)) => own_target = false,
+                Some(Op::GotoFrame(frame)) if own_target => {
+                    if start <= *frame && *frame < end {
+                        *frame = Frame(frame.0 - start.0);
+                    } else {
+                        outside.push(*frame);
+                        // NB: `GotoFrame` has no result, so nothing refers to it.
+                        op = None;
+                    }
+                }
+                Some(Op::GotoFrame2(_, _, scene_bias)) if own_target => {
+                    *scene_bias = Frame(scene_bias.0.saturating_sub(start.0));
+                }
+                _ => {}
+            }
+            new_index.push(op.as_ref().map(|_| self.ops.len()));
+            self.ops.extend(op);
+            ops_before.push(self.ops.len());
+        }
+        self.renumber(&new_index, &ops_before);
+        outside
     }
 
     pub fn compile(actions: Vec<avm1_tree::Action>) -> Self {
@@ -71,6 +627,34 @@ impl Code {
         let mut regs = vec![];
         let mut stack = vec![];
         let mut ops = vec![];
+        let mut ops_after_action = vec![];
+        let mut bailed = None;
+
+        // Give up on compiling the rest of the actions.
+        macro_rules! bail {
+            ($($msg:tt)*) => {{
+                let msg = format!($($msg)*);
+                eprintln!("avm1: {}", msg);
+                bailed = Some((ops_after_action.len(), msg));
+                break;
+            }};
+        }
+
+        macro_rules! binary {
+            ($op:expr) => {{
+                let b = stack.pop().unwrap();
+                let a = stack.pop().unwrap();
+                ops.push(Op::Binary($op, a, b));
+                stack.push(Value::OpRes(ops.len() - 1));
+            }};
+        }
+        macro_rules! unary {
+            ($op:expr) => {{
+                let value = stack.pop().unwrap();
+                ops.push(Op::Unary($op, value));
+                stack.push(Value::OpRes(ops.len() - 1));
+            }};
+        }
 
         // HACK(eddyb) this hides the warnings / inference errors about `regs`.
         // FIXME(eddyb) remove after register writes are implemented.
@@ -81,15 +665,62 @@ impl Code {
             match action {
                 avm1_tree::Action::Play => ops.push(Op::Play),
                 avm1_tree::Action::Stop => ops.push(Op::Stop),
+                avm1_tree::Action::NextFrame => ops.push(Op::NextFrame),
+                avm1_tree::Action::PrevFrame => ops.push(Op::PrevFrame),
                 avm1_tree::Action::GotoFrame(goto) => {
                     ops.push(Op::GotoFrame(Frame(goto.frame as u16)));
                 }
                 avm1_tree::Action::GotoLabel(goto) => {
                     ops.push(Op::GotoLabel(goto.label));
                 }
+                avm1_tree::Action::GotoFrame2(goto) => {
+                    let frame = stack.pop().unwrap();
+                    ops.push(Op::GotoFrame2(
+                        frame,
+                        goto.play,
+                        Frame(goto.scene_bias as u16),
+                    ));
+                }
                 avm1_tree::Action::GetUrl(get_url) => {
                     ops.push(Op::GetUrl(get_url.url, get_url.target));
                 }
+                avm1_tree::Action::GetUrl2(get_url) => {
+                    let target = stack.pop().unwrap();
+                    let url = stack.pop().unwrap();
+                    let method = SendVars::from(get_url.send_vars_method);
+                    if get_url.load_variables {
+                        ops.push(Op::LoadVariables(url, target, method));
+                    } else if get_url.load_target {
+                        ops.push(Op::LoadMovie(url, target, method));
+                    } else {
+                        ops.push(Op::GetUrl2(url, target, method));
+                    }
+                }
+
+                avm1_tree::Action::SetTarget(set_target) => {
+                    ops.push(Op::SetTarget(Value::Str(set_target.target_name)));
+                }
+                avm1_tree::Action::SetTarget2 => {
+                    let target = stack.pop().unwrap();
+                    ops.push(Op::SetTarget(target));
+                }
+                avm1_tree::Action::StopSounds => ops.push(Op::StopSounds),
+                avm1_tree::Action::ToggleQuality => ops.push(Op::ToggleQuality),
+                avm1_tree::Action::Trace => {
+                    let value = stack.pop().unwrap();
+                    ops.push(Op::Trace(value));
+                }
+
+                avm1_tree::Action::CloneSprite => {
+                    let depth = stack.pop().unwrap();
+                    let name = stack.pop().unwrap();
+                    let source = stack.pop().unwrap();
+                    ops.push(Op::CloneSprite(source, name, depth));
+                }
+                avm1_tree::Action::RemoveSprite => {
+                    let target = stack.pop().unwrap();
+                    ops.push(Op::RemoveSprite(target));
+                }
 
                 // All of frames are loaded ahead of time, no waiting needed.
                 avm1_tree::Action::WaitForFrame(_) => {}
@@ -123,22 +754,115 @@ impl Code {
                         ops.push(Op::GetVar(name));
                         stack.push(Value::OpRes(ops.len() - 1));
                     }
-                    name => {
-                        eprintln!("avm1: too dynamic GetVar({:?})", name);
-                        break;
-                    }
+                    name => bail!("too dynamic GetVar({:?})", name),
                 },
                 avm1_tree::Action::SetVariable => {
                     let value = stack.pop().unwrap();
                     match stack.pop().unwrap() {
-                        Value::Str(name) => {
-                            ops.push(Op::SetVar(name, value));
+                        Value::Str(name) => ops.push(Op::SetVar(name, value)),
+                        name => bail!("too dynamic SetVar({:?}, {:?})", name, value),
+                    }
+                }
+                avm1_tree::Action::GetProperty => {
+                    let index = stack.pop().unwrap();
+                    let target = stack.pop().unwrap();
+                    match index.as_i32().and_then(Property::from_index) {
+                        Some(prop) => {
+                            ops.push(Op::GetProperty(target, prop));
                             stack.push(Value::OpRes(ops.len() - 1));
                         }
-                        name => {
-                            eprintln!("avm1: too dynamic SetVar({:?}, {:?})", name, value);
-                            break;
+                        None => bail!("too dynamic GetProperty({:?}, {:?})", target, index),
+                    }
+                }
+                avm1_tree::Action::SetProperty => {
+                    let value = stack.pop().unwrap();
+                    let index = stack.pop().unwrap();
+                    let target = stack.pop().unwrap();
+                    match index.as_i32().and_then(Property::from_index) {
+                        Some(prop) => ops.push(Op::SetProperty(target, prop, value)),
+                        None => bail!(
+                            "too dynamic SetProperty({:?}, {:?}, {:?})",
+                            target,
+                            index,
+                            value
+                        ),
+                    }
+                }
+                avm1_tree::Action::GetMember => {
+                    let name = stack.pop().unwrap();
+                    let object = stack.pop().unwrap();
+                    ops.push(Op::GetMember(object, name));
+                    stack.push(Value::OpRes(ops.len() - 1));
+                }
+                avm1_tree::Action::SetMember => {
+                    let value = stack.pop().unwrap();
+                    let name = stack.pop().unwrap();
+                    let object = stack.pop().unwrap();
+                    ops.push(Op::SetMember(object, name, value));
+                }
+
+                avm1_tree::Action::Add2 => binary!(BinOp::Add),
+                avm1_tree::Action::Subtract => binary!(BinOp::Sub),
+                avm1_tree::Action::Multiply => binary!(BinOp::Mul),
+                avm1_tree::Action::Divide => binary!(BinOp::Div),
+                avm1_tree::Action::Modulo => binary!(BinOp::Mod),
+                avm1_tree::Action::Equals2 => binary!(BinOp::Eq),
+                avm1_tree::Action::StrictEquals => binary!(BinOp::StrictEq),
+                avm1_tree::Action::Less2 => binary!(BinOp::Lt),
+                avm1_tree::Action::Greater => binary!(BinOp::Gt),
+                avm1_tree::Action::BitAnd => binary!(BinOp::BitAnd),
+                avm1_tree::Action::BitOr => binary!(BinOp::BitOr),
+                avm1_tree::Action::BitXor => binary!(BinOp::BitXor),
+                avm1_tree::Action::BitLShift => binary!(BinOp::Shl),
+                avm1_tree::Action::BitRShift => binary!(BinOp::Shr),
+                avm1_tree::Action::BitURShift => binary!(BinOp::UShr),
+                avm1_tree::Action::Add => binary!(BinOp::NumAdd),
+                avm1_tree::Action::Equals => binary!(BinOp::NumEq),
+                avm1_tree::Action::Less => binary!(BinOp::NumLt),
+                avm1_tree::Action::StringAdd => binary!(BinOp::StrAdd),
+                avm1_tree::Action::StringEquals => binary!(BinOp::StrEq),
+                avm1_tree::Action::StringLess => binary!(BinOp::StrLt),
+                avm1_tree::Action::StringGreater => binary!(BinOp::StrGt),
+                avm1_tree::Action::Not => unary!(UnOp::Not),
+                avm1_tree::Action::Increment => unary!(UnOp::Increment),
+                avm1_tree::Action::Decrement => unary!(UnOp::Decrement),
+                avm1_tree::Action::ToNumber => unary!(UnOp::ToNumber),
+                avm1_tree::Action::ToString => unary!(UnOp::ToString),
+
+                avm1_tree::Action::InitArray => {
+                    let count = stack.pop().unwrap();
+                    match count.as_i32() {
+                        Some(count) => {
+                            let elems = (0..count).map(|_| stack.pop().unwrap()).collect();
+                            ops.push(Op::InitArray(elems));
+                            stack.push(Value::OpRes(ops.len() - 1));
                         }
+                        None => bail!("too dynamic InitArray({:?})", count),
+                    }
+                }
+                avm1_tree::Action::InitObject => {
+                    let count = stack.pop().unwrap();
+                    let count = match count.as_i32() {
+                        Some(count) => count,
+                        None => bail!("too dynamic InitObject({:?})", count),
+                    };
+                    let pairs: Vec<_> = (0..count)
+                        .map(|_| {
+                            let value = stack.pop().unwrap();
+                            (stack.pop().unwrap(), value)
+                        })
+                        .collect();
+                    let props = pairs
+                        .iter()
+                        .rev()
+                        .map(|(name, value)| Some((name.as_str()?.to_string(), value.clone())))
+                        .collect();
+                    match props {
+                        Some(props) => {
+                            ops.push(Op::InitObject(props));
+                            stack.push(Value::OpRes(ops.len() - 1));
+                        }
+                        None => bail!("too dynamic InitObject({:?})", pairs),
                     }
                 }
                 avm1_tree::Action::CallFunction => {
@@ -151,15 +875,42 @@ impl Code {
                             ops.push(Op::Call(Value::OpRes(ops.len() - 1), args));
                             stack.push(Value::OpRes(ops.len() - 1));
                         }
-                        (name, _) => {
-                            eprintln!(
-                                "avm1: too dynamic CallFunction({:?}, {:?})",
-                                name, arg_count
-                            );
-                            break;
+                        (name, _) => bail!("too dynamic CallFunction({:?}, {:?})", name, arg_count),
+                    }
+                }
+                avm1_tree::Action::NewObject => {
+                    let name = stack.pop().unwrap();
+                    let arg_count = stack.pop().unwrap();
+                    match (name, arg_count.as_i32()) {
+                        (Value::Str(name), Some(arg_count)) => {
+                            let args = (0..arg_count).map(|_| stack.pop().unwrap()).collect();
+                            ops.push(Op::GetVar(name));
+                            ops.push(Op::New(Value::OpRes(ops.len() - 1), args));
+                            stack.push(Value::OpRes(ops.len() - 1));
                         }
+                        (name, _) => bail!("too dynamic NewObject({:?}, {:?})", name, arg_count),
                     }
                 }
+
+                // These are implemented by the runtime's global functions.
+                avm1_tree::Action::RandomNumber => {
+                    let max = stack.pop().unwrap();
+                    ops.push(Op::GetVar("random".to_string()));
+                    ops.push(Op::Call(Value::OpRes(ops.len() - 1), vec![max]));
+                    stack.push(Value::OpRes(ops.len() - 1));
+                }
+                avm1_tree::Action::ToInteger => {
+                    let value = stack.pop().unwrap();
+                    ops.push(Op::GetVar("int".to_string()));
+                    ops.push(Op::Call(Value::OpRes(ops.len() - 1), vec![value]));
+                    stack.push(Value::OpRes(ops.len() - 1));
+                }
+                avm1_tree::Action::GetTime => {
+                    ops.push(Op::GetVar("getTimer".to_string()));
+                    ops.push(Op::Call(Value::OpRes(ops.len() - 1), vec![]));
+                    stack.push(Value::OpRes(ops.len() - 1));
+                }
+
                 avm1_tree::Action::CallMethod => {
                     let mut name = stack.pop().unwrap();
                     let this = stack.pop().unwrap();
@@ -182,19 +933,493 @@ impl Code {
                             ops.push(Op::CallMethod(this, name, args));
                             stack.push(Value::OpRes(ops.len() - 1));
                         }
-                        (name, _) => {
-                            eprintln!("avm1: too dynamic CallMethod({:?}, {:?})", name, arg_count);
-                            break;
+                        (name, _) => bail!("too dynamic CallMethod({:?}, {:?})", name, arg_count),
+                    }
+                }
+                _ => bail!("unknown action: {:?}", action),
+            }
+            ops_after_action.push(ops.len());
+        }
+
+        Code {
+            ops,
+            ops_after_action,
+            bailed,
+        }
+    }
+
+    // Fold operators and `int(...)` calls on constants, remove unused results
+    // of ops without side-effects, and evaluate results used only once where
+    // they're used, instead of keeping them in temporaries (as long as that
+    // preserves the order in which ops with side-effects are evaluated).
+    pub fn optimize(&mut self) {
+        let mut ops: Vec<_> = mem::take(&mut self.ops).into_iter().map(Some).collect();
+
+        let mut uses = vec![0; ops.len()];
+        for op in ops.iter().flatten() {
+            for value in op.values() {
+                if let Value::OpRes(i) = *value {
+                    uses[i] += 1;
+                }
+            }
+        }
+        let unuse = |uses: &mut Vec<usize>, op: &Op| {
+            for value in op.values() {
+                if let Value::OpRes(i) = *value {
+                    uses[i] -= 1;
+                }
+            }
+        };
+
+        // Replace the results of operators and `int(...)` on constants by constants.
+        let mut consts = BTreeMap::new();
+        for i in 0..ops.len() {
+            let mut op = ops[i].take().unwrap();
+            for value in op.values_mut() {
+                if let Value::OpRes(j) = *value {
+                    if let Some(c) = consts.get(&j) {
+                        *value = Value::clone(c);
+                    }
+                }
+            }
+            let folded = match &op {
+                // `ToInteger` (see `Code::compile`), or `int(...)` calls.
+                Op::Call(Value::OpRes(callee), args) => match (&ops[*callee], &args[..]) {
+                    (Some(Op::GetVar(name)), [arg]) if name == "int" => {
+                        arg.to_int32().map(Value::I32)
+                    }
+                    _ => None,
+                },
+                Op::Binary(op, a, b) => op.fold(a, b),
+                Op::Unary(op, a) => op.fold(a),
+                _ => None,
+            };
+            match folded {
+                Some(value) => {
+                    unuse(&mut uses, &op);
+                    consts.insert(i, value);
+                }
+                None => ops[i] = Some(op),
+            }
+        }
+
+        // Remove unused results (and, transitively, their inputs).
+        for i in (0..ops.len()).rev() {
+            if uses[i] == 0 && matches!(&ops[i], Some(op) if op.is_pure()) {
+                unuse(&mut uses, &ops[i].take().unwrap());
+            }
+        }
+
+        // For each op, all of the ops evaluated as part of it
+        // (i.e. itself and the ones moved into it), and whether
+        // none of them have side-effects.
+        let mut groups: Vec<_> = ops
+            .iter()
+            .enumerate()
+            .map(|(i, op)| (vec![i], op.iter().all(|op| op.is_pure())))
+            .collect();
+        let mut statements = vec![];
+        for i in 0..ops.len() {
+            let mut op = match ops[i].take() {
+                Some(op) => op,
+                None => continue,
+            };
+            let mut values = op.values_mut();
+            let candidates: Vec<_> = values
+                .iter()
+                .enumerate()
+                .filter_map(|(v, value)| match **value {
+                    Value::OpRes(j) if uses[j] == 1 && ops[j].is_some() => Some((v, j)),
+                    _ => None,
+                })
+                .collect();
+
+            // Try moving ops in, starting with the last one evaluated,
+            // as that's the most likely to not be reordered.
+            let mut chosen: Vec<(usize, usize)> = vec![];
+            for &candidate in candidates.iter().rev() {
+                chosen.insert(0, candidate);
+                let start = chosen
+                    .iter()
+                    .flat_map(|&(_, j)| &groups[j].0)
+                    .min()
+                    .copied()
+                    .unwrap();
+
+                // The order ops would be evaluated in, with the chosen ones
+                // moved after the rest (in the order `op` evaluates them).
+                let order: Vec<_> = statements
+                    .iter()
+                    .filter(|&&j| j >= start && !chosen.iter().any(|&(_, c)| c == j))
+                    .chain(chosen.iter().map(|(_, j)| j))
+                    .flat_map(|&j| {
+                        let (members, pure) = &groups[j];
+                        members.iter().map(move |&k| (j, k, *pure))
+                    })
+                    .collect();
+                let reordered = order.iter().enumerate().any(|(a, &(ga, ka, pa))| {
+                    order[a + 1..]
+                        .iter()
+                        .any(|&(gb, kb, pb)| ga != gb && ka > kb && !(pa && pb))
+                });
+                if reordered {
+                    chosen.remove(0);
+                }
+            }
+
+            for &(v, j) in &chosen {
+                *values[v] = Value::Op(Box::new(ops[j].take().unwrap()));
+                statements.retain(|&s| s != j);
+                let (members, pure) = mem::replace(&mut groups[j], (vec![], true));
+                groups[i].0.extend(members);
+                groups[i].1 &= pure;
+            }
+            ops[i] = Some(op);
+            statements.push(i);
+        }
+
+        // Renumber the remaining ops, and expand constant `GotoFrame2`s
+        // (which can't be used as values, so they don't need numbering).
+        let mut new_index = vec![None; ops.len()];
+        let mut ops_before = vec![0];
+        for (i, op) in ops.into_iter().enumerate() {
+            match op {
+                Some(Op::GotoFrame2(frame, play, scene_bias)) => {
+                    let goto = match &frame {
+                        // NB: labels take precedence over frame numbers.
+                        Value::Str(label) if !label.contains(':') && !maybe_numeric(label) => {
+                            Some(Op::GotoLabel(label.clone()))
+                        }
+                        Value::Str(_) => None,
+                        _ => frame
+                            .as_i32()
+                            .filter(|&n| n >= 1)
+                            .and_then(|n| {
+                                u16::try_from(i64::from(n) - 1 + i64::from(scene_bias.0)).ok()
+                            })
+                            .map(|frame| Op::GotoFrame(Frame(frame))),
+                    };
+                    match goto {
+                        Some(goto) => {
+                            self.ops.push(goto);
+                            self.ops.push(if play { Op::Play } else { Op::Stop });
                         }
+                        None => self.ops.push(Op::GotoFrame2(frame, play, scene_bias)),
                     }
                 }
-                _ => {
-                    eprintln!("unknown action: {:?}", action);
-                    break;
+                Some(op) => {
+                    new_index[i] = Some(self.ops.len());
+                    self.ops.push(op);
                 }
+                None => {}
             }
+            ops_before.push(self.ops.len());
+        }
+        self.renumber(&new_index, &ops_before);
+    }
+
+    // Update `Value::OpRes` and `ops_after_action` after removing ops,
+    // given the new indices of the old ops, and how many new ops come
+    // before each old op (plus one more entry, for the end).
+    fn renumber(&mut self, new_index: &[Option<usize>], ops_before: &[usize]) {
+        fn renumber(value: &mut Value, new_index: &[Option<usize>]) {
+            match value {
+                Value::OpRes(i) => *i = new_index[*i].unwrap(),
+                Value::Op(op) => {
+                    for value in op.values_mut() {
+                        renumber(value, new_index);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for op in &mut self.ops {
+            for value in op.values_mut() {
+                renumber(value, new_index);
+            }
+        }
+        for end in &mut self.ops_after_action {
+            *end = ops_before[*end];
+        }
+    }
+}
+
+// Whether JS might consider a frame label to be a number (see `gotoFrame2`
+// in the runtime), conservatively (e.g. `"Infinity"` and `" 1"` are numbers).
+fn maybe_numeric(label: &str) -> bool {
+    let label = label.trim();
+    label.is_empty()
+        || label.starts_with(|c: char| c.is_ascii_digit() || c == '.' || c == '+' || c == '-')
+        || label.ends_with("Infinity")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use avm1_tree::actions;
+
+    fn push(values: Vec<avm1_tree::Value>) -> avm1_tree::Action {
+        avm1_tree::Action::Push(actions::Push { values })
+    }
+
+    fn string(s: &str) -> avm1_tree::Value {
+        avm1_tree::Value::String(s.to_string())
+    }
+
+    // `f(args...)`, with `args` already pushed (in reverse).
+    fn call(f: &str, arg_count: i32) -> Vec<avm1_tree::Action> {
+        vec![
+            push(vec![avm1_tree::Value::Sint32(arg_count), string(f)]),
+            avm1_tree::Action::CallFunction,
+        ]
+    }
+
+    fn optimize(actions: Vec<avm1_tree::Action>) -> Vec<String> {
+        let mut code = Code::compile(actions);
+        assert_eq!(code.bailed, None);
+        code.optimize();
+        code.ops.iter().map(|op| format!("{:?}", op)).collect()
+    }
+
+    #[test]
+    fn get_var_moved_into_use() {
+        // `trace(x)`
+        assert_eq!(
+            optimize(vec![
+                push(vec![string("x")]),
+                avm1_tree::Action::GetVariable,
+                avm1_tree::Action::Trace,
+            ]),
+            [r#"Trace(Op(GetVar("x")))"#]
+        );
+    }
+
+    #[test]
+    fn get_var_not_moved_past_set_var() {
+        // `var a = x; x = 1; trace(a);`, i.e. `x` is read before it's written.
+        assert_eq!(
+            optimize(vec![
+                push(vec![string("x")]),
+                avm1_tree::Action::GetVariable,
+                push(vec![string("x"), avm1_tree::Value::Sint32(1)]),
+                avm1_tree::Action::SetVariable,
+                avm1_tree::Action::Trace,
+            ]),
+            [
+                r#"GetVar("x")"#,
+                r#"SetVar("x", I32(1))"#,
+                "Trace(OpRes(0))",
+            ]
+        );
+    }
+
+    #[test]
+    fn get_var_not_moved_past_set_target() {
+        // `x` is read from the original target, not from `clip`.
+        assert_eq!(
+            optimize(vec![
+                push(vec![string("x")]),
+                avm1_tree::Action::GetVariable,
+                avm1_tree::Action::SetTarget(actions::SetTarget {
+                    target_name: "clip".to_string(),
+                }),
+                avm1_tree::Action::Trace,
+            ]),
+            [
+                r#"GetVar("x")"#,
+                r#"SetTarget(Str("clip"))"#,
+                "Trace(OpRes(0))",
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_calls() {
+        // `trace(f(x, y))`, all evaluated in place.
+        let mut actions = vec![
+            push(vec![string("y")]),
+            avm1_tree::Action::GetVariable,
+            push(vec![string("x")]),
+            avm1_tree::Action::GetVariable,
+        ];
+        actions.extend(call("f", 2));
+        actions.push(avm1_tree::Action::Trace);
+        assert_eq!(
+            optimize(actions),
+            [r#"Trace(Op(Call(Op(GetVar("f")), [Op(GetVar("x")), Op(GetVar("y"))])))"#]
+        );
+
+        // `trace(f(g(1)))`, where `f` is only looked up after `g(1)` is
+        // called (which could change `f`), so `g(1)` is kept in a temporary.
+        let mut actions = vec![push(vec![avm1_tree::Value::Sint32(1)])];
+        actions.extend(call("g", 1));
+        actions.extend(call("f", 1));
+        actions.push(avm1_tree::Action::Trace);
+        assert_eq!(
+            optimize(actions),
+            [
+                r#"Call(Op(GetVar("g")), [I32(1)])"#,
+                r#"Trace(Op(Call(Op(GetVar("f")), [OpRes(0)])))"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_calls_evaluated_in_order() {
+        // `trace(f(g(1), h()))`, with `h()` called before `g(1)`, as the
+        // arguments are pushed in reverse, so neither can be moved.
+        let mut actions = call("h", 0);
+        actions.push(push(vec![avm1_tree::Value::Sint32(1)]));
+        actions.extend(call("g", 1));
+        actions.extend(call("f", 2));
+        actions.push(avm1_tree::Action::Trace);
+        assert_eq!(
+            optimize(actions),
+            [
+                r#"Call(Op(GetVar("h")), [])"#,
+                r#"Call(Op(GetVar("g")), [I32(1)])"#,
+                r#"Trace(Op(Call(Op(GetVar("f")), [OpRes(1), OpRes(0)])))"#,
+            ]
+        );
+    }
+
+    // `trace(a op b)`, for each of `(a, op, b)`.
+    fn trace_binary(
+        cases: Vec<(avm1_tree::Value, avm1_tree::Action, avm1_tree::Value)>,
+    ) -> Vec<String> {
+        let mut actions = vec![];
+        for (a, op, b) in cases {
+            actions.push(push(vec![a, b]));
+            actions.push(op);
+            actions.push(avm1_tree::Action::Trace);
+        }
+        optimize(actions)
+    }
+
+    #[test]
+    fn operators_folded() {
+        use avm1_tree::Action::*;
+        use avm1_tree::Value::*;
+        assert_eq!(
+            trace_binary(vec![
+                (Sint32(1), Add2, Float64(0.5)),
+                (String("a".to_string()), Add2, Sint32(1)),
+                (Sint32(1), Add2, String("2".to_string())),
+                (String("1".to_string()), Add, String("2".to_string())),
+                (Sint32(1), StringAdd, Boolean(true)),
+                (Sint32(1), Divide, Sint32(3)),
+                (Sint32(7), Modulo, Sint32(-4)),
+                (Sint32(-1), BitURShift, Sint32(28)),
+                (Float64(3.9), BitOr, Sint32(0)),
+                (String("10".to_string()), Less2, String("9".to_string())),
+                (Sint32(10), Less2, String("9".to_string())),
+                (Undefined, Less2, Sint32(1)),
+                (String("1".to_string()), Equals2, Sint32(1)),
+                (String("1".to_string()), StrictEquals, Sint32(1)),
+                (Null, Equals2, Undefined),
+                (Null, Equals2, Sint32(0)),
+                (Sint32(2), StringGreater, Sint32(10)),
+            ]),
+            [
+                "Trace(F64(1.5))",
+                r#"Trace(Str("a1"))"#,
+                r#"Trace(Str("12"))"#,
+                "Trace(I32(3))",
+                r#"Trace(Str("1true"))"#,
+                "Trace(F64(0.3333333333333333))",
+                "Trace(I32(3))",
+                "Trace(I32(15))",
+                "Trace(I32(3))",
+                "Trace(Bool(true))",
+                "Trace(Bool(false))",
+                "Trace(Bool(false))",
+                "Trace(Bool(true))",
+                "Trace(Bool(false))",
+                "Trace(Bool(true))",
+                "Trace(Bool(false))",
+                "Trace(Bool(true))",
+            ]
+        );
+
+        // `trace(!"")`, `trace(++"1.5")` and `trace((0 - (3 - 5)) * " 4 ")`.
+        assert_eq!(
+            optimize(vec![
+                push(vec![string("")]),
+                Not,
+                Trace,
+                push(vec![string("1.5")]),
+                Increment,
+                Trace,
+                push(vec![Sint32(0), Sint32(3), Sint32(5)]),
+                Subtract,
+                Subtract,
+                push(vec![string(" 4 ")]),
+                Multiply,
+                Trace,
+            ]),
+            ["Trace(Bool(true))", "Trace(F64(2.5))", "Trace(I32(8))"]
+        );
+    }
+
+    #[test]
+    fn operators_not_folded() {
+        use avm1_tree::Action::*;
+        use avm1_tree::Value::*;
+        // Results JS would print differently, or which aren't valid JS.
+        assert_eq!(
+            trace_binary(vec![
+                (Sint32(1), Divide, Sint32(0)),
+                (String("0x10".to_string()), Add, Sint32(0)),
+                (Float64(1e21), StringAdd, string("")),
+            ]),
+            [
+                "Trace(Op(Binary(Div, I32(1), I32(0))))",
+                r#"Trace(Op(Binary(NumAdd, Str("0x10"), I32(0))))"#,
+                r#"Trace(Op(Binary(StrAdd, F64(1e21), Str(""))))"#,
+            ]
+        );
+
+        // `x + 1;`, whose result is unused, but which can still call
+        // `x.valueOf()`, so it can't be removed.
+        assert_eq!(
+            optimize(vec![
+                push(vec![string("x")]),
+                GetVariable,
+                push(vec![Sint32(1)]),
+                Add2,
+                Pop,
+            ]),
+            [r#"Binary(Add, Op(GetVar("x")), I32(1))"#]
+        );
+    }
+
+    #[test]
+    fn rebase_frames() {
+        fn goto(frame: usize) -> avm1_tree::Action {
+            avm1_tree::Action::GotoFrame(actions::GotoFrame { frame })
         }
 
-        Code { ops }
+        // `gotoAndStop(6); trace(f()); gotoAndStop(4);`, in a scene with
+        // frames `2..5`, where going to (0-based) frame 5 has to be dropped.
+        let mut actions = vec![goto(5), avm1_tree::Action::Stop];
+        actions.extend(call("f", 0));
+        actions.extend(vec![avm1_tree::Action::Trace, goto(3)]);
+        let mut code = Code::compile(actions);
+        assert_eq!(code.bailed, None);
+        assert_eq!(code.rebase_frames(Frame(2), Frame(5)), [Frame(5)]);
+        assert_eq!(
+            code.ops
+                .iter()
+                .map(|op| format!("{:?}", op))
+                .collect::<Vec<_>>(),
+            [
+                "Stop",
+                r#"GetVar("f")"#,
+                "Call(OpRes(1), [])",
+                "Trace(OpRes(2))",
+                "GotoFrame(Frame(1))",
+            ]
+        );
+        assert_eq!(code.ops_after_action, [0, 1, 1, 3, 4, 5]);
     }
 }
@@ -0,0 +1,851 @@
+// Parsing of ABC (ActionScript Byte Code) files, as found in `DoABC` tags,
+// into a structure which mirrors the file format (see the "ActionScript
+// Virtual Machine 2 Overview" document), with indices left unresolved.
+//
+// FIXME nothing executes AVM2 code yet, this only exists so that
+// AS3 movies can be inspected (and, eventually, compiled).
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str;
+use swf_types as swf;
+
+// Whether the movie is meant to be played by AVM2 (i.e. uses AS3), in which
+// case Flash Player ignores all AVM1 actions (`DoAction`, `DoInitAction`).
+pub fn is_as3(movie: &swf::Movie) -> bool {
+    movie.tags.iter().any(|tag| match tag {
+        swf::Tag::FileAttributes(attrs) => attrs.use_as3,
+        swf::Tag::DoAbc(_) | swf::Tag::SymbolClass(_) => true,
+        _ => false,
+    })
+}
+
+#[derive(Debug)]
+pub enum Error {
+    UnexpectedEnd,
+    UnknownNamespaceKind(u8),
+    UnknownMultinameKind(u8),
+    UnknownTraitKind(u8),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnexpectedEnd => write!(f, "unexpected end of data"),
+            Error::UnknownNamespaceKind(kind) => write!(f, "unknown namespace kind 0x{:02x}", kind),
+            Error::UnknownMultinameKind(kind) => write!(f, "unknown multiname kind 0x{:02x}", kind),
+            Error::UnknownTraitKind(kind) => write!(f, "unknown trait kind 0x{:02x}", kind),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NamespaceKind {
+    Namespace,
+    Package,
+    PackageInternal,
+    Protected,
+    Explicit,
+    StaticProtected,
+    Private,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Namespace {
+    pub kind: NamespaceKind,
+    pub name: u32,
+}
+
+#[derive(Clone, Debug)]
+pub enum Multiname {
+    QName {
+        ns: u32,
+        name: u32,
+        attribute: bool,
+    },
+    RtQName {
+        name: u32,
+        attribute: bool,
+    },
+    RtQNameL {
+        attribute: bool,
+    },
+    Multiname {
+        name: u32,
+        ns_set: u32,
+        attribute: bool,
+    },
+    MultinameL {
+        ns_set: u32,
+        attribute: bool,
+    },
+    TypeName {
+        name: u32,
+        params: Vec<u32>,
+    },
+}
+
+// All indices into these start at `1`, with `0` usually meaning "none"
+// (or "any", for names), and the first entry of each `Vec` being unused.
+#[derive(Default, Debug)]
+pub struct ConstantPool<'a> {
+    pub ints: Vec<i32>,
+    pub uints: Vec<u32>,
+    pub doubles: Vec<f64>,
+    pub strings: Vec<Cow<'a, str>>,
+    pub namespaces: Vec<Namespace>,
+    pub ns_sets: Vec<Vec<u32>>,
+    pub multinames: Vec<Multiname>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct DefaultValue {
+    pub kind: u8,
+    pub index: u32,
+}
+
+#[derive(Debug)]
+pub struct Method {
+    pub name: u32,
+    pub params: Vec<u32>,
+    pub return_type: u32,
+    pub flags: u8,
+    pub optional: Vec<DefaultValue>,
+    pub param_names: Vec<u32>,
+}
+
+#[derive(Debug)]
+pub struct Metadata {
+    pub name: u32,
+    pub items: Vec<(u32, u32)>,
+}
+
+#[derive(Debug)]
+pub enum TraitKind {
+    Slot {
+        slot_id: u32,
+        type_name: u32,
+        value: Option<DefaultValue>,
+    },
+    Const {
+        slot_id: u32,
+        type_name: u32,
+        value: Option<DefaultValue>,
+    },
+    Method {
+        disp_id: u32,
+        method: u32,
+    },
+    Getter {
+        disp_id: u32,
+        method: u32,
+    },
+    Setter {
+        disp_id: u32,
+        method: u32,
+    },
+    Class {
+        slot_id: u32,
+        class: u32,
+    },
+    Function {
+        slot_id: u32,
+        method: u32,
+    },
+}
+
+#[derive(Debug)]
+pub struct Trait {
+    pub name: u32,
+    pub kind: TraitKind,
+    pub is_final: bool,
+    pub is_override: bool,
+    pub metadata: Vec<u32>,
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub name: u32,
+    pub super_name: u32,
+    pub flags: u8,
+    pub protected_ns: Option<u32>,
+    pub interfaces: Vec<u32>,
+    pub init: u32,
+    pub traits: Vec<Trait>,
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub init: u32,
+    pub traits: Vec<Trait>,
+}
+
+#[derive(Debug)]
+pub struct Script {
+    pub init: u32,
+    pub traits: Vec<Trait>,
+}
+
+#[derive(Debug)]
+pub struct Exception {
+    pub from: u32,
+    pub to: u32,
+    pub target: u32,
+    pub exception_type: u32,
+    pub var_name: u32,
+}
+
+#[derive(Debug)]
+pub struct MethodBody<'a> {
+    pub method: u32,
+    pub max_stack: u32,
+    pub local_count: u32,
+    pub init_scope_depth: u32,
+    pub max_scope_depth: u32,
+    // FIXME decode the instructions.
+    pub code: &'a [u8],
+    pub exceptions: Vec<Exception>,
+    pub traits: Vec<Trait>,
+}
+
+#[derive(Debug)]
+pub struct Abc<'a> {
+    pub minor_version: u16,
+    pub major_version: u16,
+    pub constant_pool: ConstantPool<'a>,
+    pub methods: Vec<Method>,
+    pub metadata: Vec<Metadata>,
+    // `instances[i]` and `classes[i]` describe the same class,
+    // the former its instances, the latter its static side.
+    pub instances: Vec<Instance>,
+    pub classes: Vec<Class>,
+    pub scripts: Vec<Script>,
+    pub method_bodies: Vec<MethodBody<'a>>,
+}
+
+impl<'a> Abc<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        Reader { data }.abc()
+    }
+
+    pub fn string(&self, index: u32) -> Option<&str> {
+        if index == 0 {
+            return None;
+        }
+        self.constant_pool
+            .strings
+            .get(index as usize)
+            .map(|s| &s[..])
+    }
+
+    // A human-readable name for a multiname, e.g. `flash.display::MovieClip`.
+    pub fn multiname_to_string(&self, index: u32) -> String {
+        let pool = &self.constant_pool;
+        let name = |index| self.string(index).unwrap_or("*");
+        let multiname = match pool.multinames.get(index as usize) {
+            Some(multiname) if index != 0 => multiname,
+            _ => return "*".to_string(),
+        };
+        match multiname {
+            Multiname::QName { ns, name: n, .. } => {
+                let ns = pool
+                    .namespaces
+                    .get(*ns as usize)
+                    .and_then(|ns| self.string(ns.name))
+                    .unwrap_or("");
+                if ns.is_empty() {
+                    name(*n).to_string()
+                } else {
+                    format!("{}::{}", ns, name(*n))
+                }
+            }
+            Multiname::RtQName { name: n, .. } | Multiname::Multiname { name: n, .. } => {
+                name(*n).to_string()
+            }
+            Multiname::RtQNameL { .. } | Multiname::MultinameL { .. } => "[]".to_string(),
+            Multiname::TypeName { name, params } => format!(
+                "{}.<{}>",
+                self.multiname_to_string(*name),
+                params
+                    .iter()
+                    .map(|&param| self.multiname_to_string(param))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    // The names of all the classes defined by this ABC file.
+    pub fn class_names(&self) -> impl Iterator<Item = String> + '_ {
+        self.instances
+            .iter()
+            .map(move |instance| self.multiname_to_string(instance.name))
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.data.len() < len {
+            return Err(Error::UnexpectedEnd);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let b = self.bytes(2)?;
+        Ok(u16::from(b[0]) | u16::from(b[1]) << 8)
+    }
+
+    fn f64(&mut self) -> Result<f64, Error> {
+        let mut bits = 0;
+        for (i, &b) in self.bytes(8)?.iter().enumerate() {
+            bits |= u64::from(b) << (i * 8);
+        }
+        Ok(f64::from_bits(bits))
+    }
+
+    // Variable-length encoding, 7 bits per byte (little-endian), with the
+    // high bit indicating whether more bytes follow (up to 5 bytes total).
+    fn var(&mut self) -> Result<(u32, u32), Error> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            value |= u32::from(b & 0x7f).wrapping_shl(shift);
+            shift += 7;
+            if b & 0x80 == 0 || shift >= 35 {
+                return Ok((value, shift));
+            }
+        }
+    }
+
+    fn u30(&mut self) -> Result<u32, Error> {
+        Ok(self.var()?.0)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(self.var()?.0)
+    }
+
+    fn s32(&mut self) -> Result<i32, Error> {
+        let (value, shift) = self.var()?;
+        let value = value as i32;
+        // Sign-extend from the last bit read.
+        if shift < 32 {
+            Ok(value << (32 - shift) >> (32 - shift))
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn list<T>(
+        &mut self,
+        mut f: impl FnMut(&mut Self) -> Result<T, Error>,
+    ) -> Result<Vec<T>, Error> {
+        let count = self.u30()?;
+        // Avoid allocating too much for corrupted counts.
+        let mut list = Vec::with_capacity((count as usize).min(self.data.len()));
+        for _ in 0..count {
+            list.push(f(self)?);
+        }
+        Ok(list)
+    }
+
+    // Constant pool lists, where the count includes the implicit entry `0`.
+    fn pool_list<T: Default>(
+        &mut self,
+        mut f: impl FnMut(&mut Self) -> Result<T, Error>,
+    ) -> Result<Vec<T>, Error> {
+        let count = self.u30()?;
+        let mut list = Vec::with_capacity((count as usize).min(self.data.len()));
+        list.push(T::default());
+        for _ in 1..count {
+            list.push(f(self)?);
+        }
+        Ok(list)
+    }
+
+    fn string(&mut self) -> Result<Cow<'a, str>, Error> {
+        let len = self.u30()?;
+        Ok(String::from_utf8_lossy(self.bytes(len as usize)?))
+    }
+
+    fn namespace(&mut self) -> Result<Namespace, Error> {
+        let kind = match self.u8()? {
+            0x08 => NamespaceKind::Namespace,
+            0x16 => NamespaceKind::Package,
+            0x17 => NamespaceKind::PackageInternal,
+            0x18 => NamespaceKind::Protected,
+            0x19 => NamespaceKind::Explicit,
+            0x1a => NamespaceKind::StaticProtected,
+            0x05 => NamespaceKind::Private,
+            kind => return Err(Error::UnknownNamespaceKind(kind)),
+        };
+        Ok(Namespace {
+            kind,
+            name: self.u30()?,
+        })
+    }
+
+    fn multiname(&mut self) -> Result<Multiname, Error> {
+        let kind = self.u8()?;
+        let attribute = matches!(kind, 0x0d | 0x10 | 0x12 | 0x0e | 0x1c);
+        Ok(match kind {
+            0x07 | 0x0d => Multiname::QName {
+                ns: self.u30()?,
+                name: self.u30()?,
+                attribute,
+            },
+            0x0f | 0x10 => Multiname::RtQName {
+                name: self.u30()?,
+                attribute,
+            },
+            0x11 | 0x12 => Multiname::RtQNameL { attribute },
+            0x09 | 0x0e => Multiname::Multiname {
+                name: self.u30()?,
+                ns_set: self.u30()?,
+                attribute,
+            },
+            0x1b | 0x1c => Multiname::MultinameL {
+                ns_set: self.u30()?,
+                attribute,
+            },
+            0x1d => Multiname::TypeName {
+                name: self.u30()?,
+                params: self.list(Self::u30)?,
+            },
+            _ => return Err(Error::UnknownMultinameKind(kind)),
+        })
+    }
+
+    fn constant_pool(&mut self) -> Result<ConstantPool<'a>, Error> {
+        Ok(ConstantPool {
+            ints: self.pool_list(Self::s32)?,
+            uints: self.pool_list(Self::u32)?,
+            doubles: self.pool_list(Self::f64)?,
+            strings: self.pool_list(Self::string)?,
+            namespaces: {
+                let count = self.u30()?;
+                let mut namespaces = vec![Namespace {
+                    kind: NamespaceKind::Namespace,
+                    name: 0,
+                }];
+                for _ in 1..count {
+                    namespaces.push(self.namespace()?);
+                }
+                namespaces
+            },
+            ns_sets: self.pool_list(|r| r.list(Self::u30))?,
+            multinames: {
+                let count = self.u30()?;
+                let mut multinames = vec![Multiname::RtQNameL { attribute: false }];
+                for _ in 1..count {
+                    multinames.push(self.multiname()?);
+                }
+                multinames
+            },
+        })
+    }
+
+    fn default_value(&mut self) -> Result<DefaultValue, Error> {
+        Ok(DefaultValue {
+            index: self.u30()?,
+            kind: self.u8()?,
+        })
+    }
+
+    fn method(&mut self) -> Result<Method, Error> {
+        let param_count = self.u30()?;
+        let return_type = self.u30()?;
+        let mut params = vec![];
+        for _ in 0..param_count {
+            params.push(self.u30()?);
+        }
+        let name = self.u30()?;
+        let flags = self.u8()?;
+        // `HAS_OPTIONAL`.
+        let optional = if flags & 0x08 != 0 {
+            self.list(Self::default_value)?
+        } else {
+            vec![]
+        };
+        // `HAS_PARAM_NAMES`.
+        let mut param_names = vec![];
+        if flags & 0x80 != 0 {
+            for _ in 0..param_count {
+                param_names.push(self.u30()?);
+            }
+        }
+        Ok(Method {
+            name,
+            params,
+            return_type,
+            flags,
+            optional,
+            param_names,
+        })
+    }
+
+    fn metadata(&mut self) -> Result<Metadata, Error> {
+        Ok(Metadata {
+            name: self.u30()?,
+            items: self.list(|r| Ok((r.u30()?, r.u30()?)))?,
+        })
+    }
+
+    fn trait_(&mut self) -> Result<Trait, Error> {
+        let name = self.u30()?;
+        let kind_and_attrs = self.u8()?;
+        let attrs = kind_and_attrs >> 4;
+        let kind = match kind_and_attrs & 0xf {
+            kind @ 0 | kind @ 6 => {
+                let slot_id = self.u30()?;
+                let type_name = self.u30()?;
+                let index = self.u30()?;
+                let value = if index != 0 {
+                    Some(DefaultValue {
+                        index,
+                        kind: self.u8()?,
+                    })
+                } else {
+                    None
+                };
+                if kind == 0 {
+                    TraitKind::Slot {
+                        slot_id,
+                        type_name,
+                        value,
+                    }
+                } else {
+                    TraitKind::Const {
+                        slot_id,
+                        type_name,
+                        value,
+                    }
+                }
+            }
+            1 => TraitKind::Method {
+                disp_id: self.u30()?,
+                method: self.u30()?,
+            },
+            2 => TraitKind::Getter {
+                disp_id: self.u30()?,
+                method: self.u30()?,
+            },
+            3 => TraitKind::Setter {
+                disp_id: self.u30()?,
+                method: self.u30()?,
+            },
+            4 => TraitKind::Class {
+                slot_id: self.u30()?,
+                class: self.u30()?,
+            },
+            5 => TraitKind::Function {
+                slot_id: self.u30()?,
+                method: self.u30()?,
+            },
+            kind => return Err(Error::UnknownTraitKind(kind)),
+        };
+        // `ATTR_Metadata`.
+        let metadata = if attrs & 0x4 != 0 {
+            self.list(Self::u30)?
+        } else {
+            vec![]
+        };
+        Ok(Trait {
+            name,
+            kind,
+            is_final: attrs & 0x1 != 0,
+            is_override: attrs & 0x2 != 0,
+            metadata,
+        })
+    }
+
+    fn instance(&mut self) -> Result<Instance, Error> {
+        let name = self.u30()?;
+        let super_name = self.u30()?;
+        let flags = self.u8()?;
+        // `CONSTANT_ClassProtectedNs`.
+        let protected_ns = if flags & 0x08 != 0 {
+            Some(self.u30()?)
+        } else {
+            None
+        };
+        Ok(Instance {
+            name,
+            super_name,
+            flags,
+            protected_ns,
+            interfaces: self.list(Self::u30)?,
+            init: self.u30()?,
+            traits: self.list(Self::trait_)?,
+        })
+    }
+
+    fn method_body(&mut self) -> Result<MethodBody<'a>, Error> {
+        Ok(MethodBody {
+            method: self.u30()?,
+            max_stack: self.u30()?,
+            local_count: self.u30()?,
+            init_scope_depth: self.u30()?,
+            max_scope_depth: self.u30()?,
+            code: {
+                let len = self.u30()?;
+                self.bytes(len as usize)?
+            },
+            exceptions: self.list(|r| {
+                Ok(Exception {
+                    from: r.u30()?,
+                    to: r.u30()?,
+                    target: r.u30()?,
+                    exception_type: r.u30()?,
+                    var_name: r.u30()?,
+                })
+            })?,
+            traits: self.list(Self::trait_)?,
+        })
+    }
+
+    fn abc(&mut self) -> Result<Abc<'a>, Error> {
+        let minor_version = self.u16()?;
+        let major_version = self.u16()?;
+        let constant_pool = self.constant_pool()?;
+        let methods = self.list(Self::method)?;
+        let metadata = self.list(Self::metadata)?;
+        let instances = self.list(Self::instance)?;
+        let mut classes = vec![];
+        for _ in 0..instances.len() {
+            classes.push(Class {
+                init: self.u30()?,
+                traits: self.list(Self::trait_)?,
+            });
+        }
+        let scripts = self.list(|r| {
+            Ok(Script {
+                init: r.u30()?,
+                traits: r.list(Self::trait_)?,
+            })
+        })?;
+        let method_bodies = self.list(Self::method_body)?;
+        Ok(Abc {
+            minor_version,
+            major_version,
+            constant_pool,
+            methods,
+            metadata,
+            instances,
+            classes,
+            scripts,
+            method_bodies,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u30(x: u32) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut x = x;
+        loop {
+            if x < 0x80 {
+                bytes.push(x as u8);
+                return bytes;
+            }
+            bytes.push(x as u8 | 0x80);
+            x >>= 7;
+        }
+    }
+
+    fn string(s: &str) -> Vec<u8> {
+        [u30(s.len() as u32), s.as_bytes().to_vec()].concat()
+    }
+
+    // A small ABC file, with a `Main` class (extending `MovieClip`), and
+    // `trait_kind` being the kind (and attributes) of its method trait.
+    fn abc(trait_kind: u8) -> Vec<u8> {
+        [
+            // Minor and major version.
+            vec![16, 0, 46, 0],
+            // `ints`: `-1`, `300`.
+            vec![3, 0xff, 0xff, 0xff, 0xff, 0x0f],
+            u30(300),
+            // `uints`: `300`.
+            vec![2],
+            u30(300),
+            // `doubles`: `1.5`.
+            vec![2],
+            1.5f64.to_le_bytes().to_vec(),
+            // `strings`.
+            vec![5],
+            string("flash.display"),
+            string("MovieClip"),
+            string("Main"),
+            string("x"),
+            // `namespaces`: `flash.display` (package), `""` (package internal).
+            vec![3, 0x16, 1, 0x17, 0],
+            // `ns_sets`: `[1, 2]`.
+            vec![2, 2, 1, 2],
+            // `multinames`: `flash.display::MovieClip`, `Main`, `x`, and `x`
+            // in either namespace.
+            vec![5, 0x07, 1, 2, 0x07, 2, 3, 0x07, 2, 4, 0x09, 4, 1],
+            // `methods`: the class initializer, and `x(a:MovieClip = -1, b)`.
+            vec![2, 0, 0, 0, 0],
+            vec![2, 1, 1, 0, 4, 0x88, 1, 1, 0x03, 3, 4],
+            // `metadata`: `[Main(x = "Main")]`.
+            vec![1, 3, 1, 4, 3],
+            // `instances`: `Main`, with a slot `x = -1`, and method `x`.
+            vec![1, 2, 1, 0x09, 1, 0, 0, 2],
+            vec![3, 0x00, 1, 0, 1, 0x03],
+            vec![4, trait_kind, 0, 1, 1, 0],
+            // `classes`: no static traits.
+            vec![0, 0],
+            // `scripts`: defining `Main`.
+            vec![1, 0, 1, 2, 0x04, 1, 0],
+            // `method_bodies`: `x`, which just returns.
+            vec![1, 1, 1, 3, 0, 1, 1, 0x47, 0, 0],
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn constant_pool() {
+        let data = abc(0x51);
+        let abc = Abc::parse(&data).unwrap();
+        assert_eq!((abc.minor_version, abc.major_version), (16, 46));
+
+        let pool = &abc.constant_pool;
+        assert_eq!(pool.ints, [0, -1, 300]);
+        assert_eq!(pool.uints, [0, 300]);
+        assert_eq!(pool.doubles, [0.0, 1.5]);
+        assert_eq!(
+            pool.strings,
+            ["", "flash.display", "MovieClip", "Main", "x"]
+        );
+        assert_eq!(
+            pool.namespaces
+                .iter()
+                .map(|ns| (ns.kind, ns.name))
+                .collect::<Vec<_>>(),
+            [
+                (NamespaceKind::Namespace, 0),
+                (NamespaceKind::Package, 1),
+                (NamespaceKind::PackageInternal, 0),
+            ]
+        );
+        assert_eq!(pool.ns_sets, [vec![], vec![1, 2]]);
+        assert_eq!(
+            (1..5)
+                .map(|i| abc.multiname_to_string(i))
+                .collect::<Vec<_>>(),
+            ["flash.display::MovieClip", "Main", "x", "x"]
+        );
+        assert_eq!(abc.class_names().collect::<Vec<_>>(), ["Main"]);
+    }
+
+    #[test]
+    fn methods() {
+        let data = abc(0x51);
+        let abc = Abc::parse(&data).unwrap();
+        assert_eq!(abc.methods.len(), 2);
+
+        let method = &abc.methods[1];
+        assert_eq!(abc.string(method.name), Some("x"));
+        assert_eq!(method.params, [1, 0]);
+        assert_eq!(method.return_type, 1);
+        assert_eq!(method.flags, 0x88);
+        assert_eq!(
+            method
+                .optional
+                .iter()
+                .map(|value| (value.kind, value.index))
+                .collect::<Vec<_>>(),
+            [(0x03, 1)]
+        );
+        assert_eq!(method.param_names, [3, 4]);
+
+        assert_eq!(abc.metadata.len(), 1);
+        assert_eq!(abc.metadata[0].items, [(4, 3)]);
+
+        assert_eq!(abc.method_bodies.len(), 1);
+        let body = &abc.method_bodies[0];
+        assert_eq!((body.method, body.local_count), (1, 3));
+        assert_eq!(body.code, [0x47]);
+    }
+
+    #[test]
+    fn traits() {
+        let data = abc(0x51);
+        let abc = Abc::parse(&data).unwrap();
+        let instance = &abc.instances[0];
+        assert_eq!(
+            abc.multiname_to_string(instance.super_name),
+            "flash.display::MovieClip"
+        );
+        assert_eq!(instance.protected_ns, Some(1));
+        assert_eq!(instance.traits.len(), 2);
+
+        let slot = &instance.traits[0];
+        assert_eq!(abc.multiname_to_string(slot.name), "x");
+        match slot.kind {
+            TraitKind::Slot {
+                slot_id: 1,
+                type_name: 0,
+                value:
+                    Some(DefaultValue {
+                        kind: 0x03,
+                        index: 1,
+                    }),
+            } => {}
+            ref kind => panic!("unexpected slot trait: {:?}", kind),
+        }
+        assert!(!slot.is_final && slot.metadata.is_empty());
+
+        let method = &instance.traits[1];
+        assert!(matches!(
+            method.kind,
+            TraitKind::Method {
+                disp_id: 0,
+                method: 1
+            }
+        ));
+        assert!(method.is_final && !method.is_override);
+        assert_eq!(method.metadata, [0]);
+
+        assert_eq!(abc.scripts.len(), 1);
+        assert!(matches!(
+            abc.scripts[0].traits[0].kind,
+            TraitKind::Class {
+                slot_id: 1,
+                class: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn invalid() {
+        let data = abc(0x51);
+        for len in 0..data.len() {
+            match Abc::parse(&data[..len]) {
+                Err(Error::UnexpectedEnd) => {}
+                result => panic!("truncated to {} bytes: {:?}", len, result.map(|_| ())),
+            }
+        }
+
+        // Only the low 4 bits are the kind, the rest are attributes.
+        let e = Abc::parse(&abc(0x57)).unwrap_err();
+        assert!(matches!(e, Error::UnknownTraitKind(7)));
+        assert_eq!(e.to_string(), "unknown trait kind 0x07");
+    }
+}
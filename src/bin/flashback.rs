@@ -1,29 +1,70 @@
-use std::{fs, path::PathBuf};
+use flashback::avm1;
+use flashback::timeline::ClipEventHandler;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 use structopt::StructOpt;
 
-#[derive(Debug,StructOpt)]
-struct Opt{
+#[derive(Debug, StructOpt)]
+#[structopt(setting = structopt::clap::AppSettings::SubcommandsNegateReqs)]
+struct Opt {
     #[structopt(long)]
-    use_js:bool,
+    use_js: bool,
+    #[structopt(long, help = "Export each scene to its own file")]
+    split_scenes: bool,
     #[structopt(required = true)]
     files: Vec<PathBuf>,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    #[structopt(about = "Print AVM1 actions next to what they compile to")]
+    Disasm {
+        #[structopt(required = true)]
+        files: Vec<PathBuf>,
+    },
 }
 
 fn main() {
     let opt = Opt::from_args();
-    for path in opt.files {
+    if let Some(Command::Disasm { files }) = opt.command {
+        for path in files {
+            let data = fs::read(&path).unwrap();
+            match swf_parser::parse_swf(&data) {
+                Ok(movie) => {
+                    println!("{}:", path.display());
+                    disasm(&movie);
+                }
+                Err(e) => eprintln!("{}: swf-parser errored: {:?}", path.display(), e),
+            }
+        }
+        return;
+    }
+    for path in &opt.files {
         let data = fs::read(&path).unwrap();
         eprint!("{}:", path.display());
         match swf_parser::parse_swf(&data) {
             Ok(movie) => {
                 // println!("{:#?}", movie);
-                let document = flashback::export::svg::export(
-                    &movie,
-                    flashback::export::svg::Config {
-                        use_js: opt.use_js,
-                    },
-                );
-                svg::save(path.with_extension("svg"), &document).unwrap();
+                let config = flashback::export::svg::Config {
+                    use_js: opt.use_js,
+                    load_import: Some(Box::new(load_import(&path))),
+                };
+                if opt.split_scenes && has_scenes(&movie) {
+                    let scenes = flashback::export::svg::export_scenes(&movie, config);
+                    // Scenes are numbered from 1, like in Flash.
+                    for (i, (name, svg_document)) in scenes.iter().enumerate() {
+                        let scene_path = path.with_extension(format!("scene{}.svg", i + 1));
+                        eprint!(" {:?} -> {}", name, scene_path.display());
+                        svg::save(scene_path, svg_document).unwrap();
+                    }
+                } else {
+                    let svg_document = flashback::export::svg::export(&movie, config);
+                    svg::save(path.with_extension("svg"), &svg_document).unwrap();
+                }
             }
             Err(e) => {
                 eprintln!("swf-parser errored: {:?}", e);
@@ -31,3 +72,113 @@ fn main() {
         }
     }
 }
+
+// Scenes are only listed by `DefineSceneAndFrameLabelData`.
+fn has_scenes(movie: &swf_types::Movie) -> bool {
+    movie.tags.iter().any(|tag| match tag {
+        swf_types::Tag::DefineSceneAndFrameLabelData(data) => !data.scenes.is_empty(),
+        _ => false,
+    })
+}
+
+// Imported movies are looked up next to the importing one, by file name.
+fn load_import(path: &Path) -> impl Fn(&str) -> Option<swf_types::Movie> {
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    move |url| {
+        let path = dir.join(Path::new(url).file_name()?);
+        let data = fs::read(&path)
+            .map_err(|e| eprintln!("{}: {}", path.display(), e))
+            .ok()?;
+        swf_parser::parse_swf(&data)
+            .map_err(|e| eprintln!("{}: swf-parser errored: {:?}", path.display(), e))
+            .ok()
+    }
+}
+
+fn disasm(movie: &swf_types::Movie) {
+    disasm_timeline("main timeline", &movie.tags);
+    for tag in &movie.tags {
+        match tag {
+            swf_types::Tag::DefineSprite(def) => {
+                disasm_timeline(&format!("sprite {}", def.id), &def.tags);
+            }
+            swf_types::Tag::DoInitAction(init) => disasm_actions(
+                &format!("sprite {}, init actions", init.sprite_id),
+                &init.actions,
+            ),
+            swf_types::Tag::DefineButton(def) => {
+                let button = flashback::button::Button::from(def);
+                for (cond_actions, handler) in def.actions.iter().zip(&button.handlers) {
+                    disasm_actions(
+                        &format!("button {}, on {:?}", def.id, handler.on),
+                        &cond_actions.actions,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn disasm_timeline(name: &str, tags: &[swf_types::Tag]) {
+    // NB: frames are shown 1-based, like in `gotoAndPlay`.
+    let mut frame = 1;
+    for tag in tags {
+        match tag {
+            swf_types::Tag::DoAction(do_action) => {
+                disasm_actions(&format!("{}, frame {}", name, frame), &do_action.actions)
+            }
+            swf_types::Tag::PlaceObject(place) => {
+                for clip_action in place.clip_actions.iter().flatten() {
+                    let handler = ClipEventHandler::from(clip_action);
+                    disasm_actions(
+                        &format!(
+                            "{}, frame {}, depth {}, on {:?}",
+                            name, frame, place.depth, handler.on
+                        ),
+                        &clip_action.actions,
+                    );
+                }
+            }
+            swf_types::Tag::ShowFrame => frame += 1,
+            _ => {}
+        }
+    }
+}
+
+// Print each action, with the ops it compiled to on its right, marking
+// the action compilation stopped at, and the ones left uncompiled after it,
+// followed by the ops left after optimizing them (see `Code::optimize`).
+fn disasm_actions(name: &str, data: &[u8]) {
+    println!("  {}:", name);
+    let code = avm1::Code::compile(avm1::Code::parse(data));
+    let mut ops = 0..0;
+    for (i, action) in avm1::Code::parse(data).iter().enumerate() {
+        let mut right = vec![];
+        match &code.bailed {
+            Some((bailed, reason)) if *bailed == i => right.push(format!("!!! bailed: {}", reason)),
+            Some((bailed, _)) if *bailed < i => right.push("(not compiled)".to_string()),
+            _ => {
+                ops = ops.end..code.ops_after_action[i];
+                for j in ops.clone() {
+                    right.push(format!("#{} = {:?}", j, code.ops[j]));
+                }
+            }
+        }
+        let left = format!("{:4}: {:?}", i, action);
+        if right.is_empty() {
+            println!("    {}", left);
+        }
+        for (j, right) in right.iter().enumerate() {
+            let left = if j == 0 { &left[..] } else { "" };
+            println!("    {:<48} | {}", left, right);
+        }
+    }
+
+    let mut optimized = code;
+    optimized.optimize();
+    println!("    optimized:");
+    for (i, op) in optimized.ops.iter().enumerate() {
+        println!("    #{} = {:?}", i, op);
+    }
+}
@@ -41,14 +41,73 @@ pub struct EventHandler {
 pub struct Button {
     pub objects: PerState<BTreeMap<Depth, Object<'static>>>,
     pub handlers: Vec<EventHandler>,
+
+    // Menu buttons can be pressed by dragging onto them with the mouse
+    // already down, and get released (not dragged out) by leaving them.
+    pub track_as_menu: bool,
+
+    // Sounds played on some of the transitions, from `DefineButtonSound`.
+    pub sounds: BTreeMap<Event, swf::ButtonSound>,
+}
+
+impl Button {
+    // `DefineButtonCxform` provides the color transform for all of the
+    // characters in a `DefineButton` (which can't have their own).
+    pub fn set_color_transform(&mut self, color_transform: &swf::ColorTransform) {
+        let color_transform = swf::ColorTransformWithAlpha {
+            red_mult: color_transform.red_mult,
+            green_mult: color_transform.green_mult,
+            blue_mult: color_transform.blue_mult,
+            red_add: color_transform.red_add,
+            green_add: color_transform.green_add,
+            blue_add: color_transform.blue_add,
+            ..swf::ColorTransformWithAlpha::default()
+        };
+        let objects = &mut self.objects;
+        for state in &mut [
+            &mut objects.up,
+            &mut objects.over,
+            &mut objects.down,
+            &mut objects.hit_test,
+        ] {
+            for object in state.values_mut() {
+                object.color_transform = color_transform;
+            }
+        }
+    }
+
+    pub fn add_sounds(&mut self, def: &swf::tags::DefineButtonSound) {
+        let sounds = [
+            (Event::HoverOut, &def.over_up_to_idle),
+            (Event::HoverIn, &def.idle_to_over_up),
+            (Event::Down, &def.over_up_to_over_down),
+            (Event::Up, &def.over_down_to_over_up),
+        ];
+        for (event, sound) in sounds.iter() {
+            if let Some(sound) = sound {
+                let info = &sound.sound_info;
+                if info.envelope_records.is_some()
+                    || info.in_point.is_some()
+                    || info.out_point.is_some()
+                    || info.sync_stop
+                {
+                    eprintln!("Button::add_sounds: unsupported SoundInfo: {:?}", sound);
+                }
+                self.sounds.insert(*event, sound.clone());
+            }
+        }
+    }
 }
 
 impl<'a> From<&'a swf::tags::DefineButton> for Button {
     fn from(button: &swf::tags::DefineButton) -> Self {
         let mut objects = PerState::<BTreeMap<Depth, Object>>::default();
         for record in &button.characters {
-            if !record.filters.is_empty() || record.blend_mode != swf::BlendMode::Normal {
-                eprintln!("Button::from: unsupported features in {:?}", record);
+            if let swf::BlendMode::Alpha | swf::BlendMode::Erase = record.blend_mode {
+                eprintln!(
+                    "Button::from: unsupported blend mode {:?}",
+                    record.blend_mode
+                );
             }
 
             let depth = Depth(record.depth);
@@ -59,16 +118,23 @@ impl<'a> From<&'a swf::tags::DefineButton> for Button {
                 name: None,
                 color_transform: record.color_transform.unwrap_or_default(),
                 ratio: None,
+                clip_actions: None,
+                filters: record.filters.clone(),
+                blend_mode: record.blend_mode,
+                visible: true,
+                cache_as_bitmap: false,
+                background_color: None,
+                clip_depth: None,
             };
 
             if record.state_up {
-                objects.up.insert(depth, object);
+                objects.up.insert(depth, object.clone());
             }
             if record.state_over {
-                objects.over.insert(depth, object);
+                objects.over.insert(depth, object.clone());
             }
             if record.state_down {
-                objects.down.insert(depth, object);
+                objects.down.insert(depth, object.clone());
             }
             if record.state_hit_test {
                 objects.hit_test.insert(depth, object);
@@ -79,9 +145,17 @@ impl<'a> From<&'a swf::tags::DefineButton> for Button {
             .actions
             .iter()
             .map(|cond_actions| {
-                let cond = cond_actions
-                    .conditions
-                    .expect("ButtonCondAction missing conditions");
+                // `DefineButton` (unlike `DefineButton2`) has no conditions,
+                // and its only actions run when the button is released.
+                let cond = match cond_actions.conditions {
+                    Some(cond) => cond,
+                    None => {
+                        return EventHandler {
+                            on: vec![Event::Up],
+                            actions: crate::avm1::Code::parse_and_compile(&cond_actions.actions),
+                        };
+                    }
+                };
                 let on = [
                     (Event::HoverIn, cond.idle_to_over_up),
                     (Event::HoverOut, cond.over_up_to_idle),
@@ -105,6 +179,11 @@ impl<'a> From<&'a swf::tags::DefineButton> for Button {
             })
             .collect();
 
-        Button { objects, handlers }
+        Button {
+            objects,
+            handlers,
+            track_as_menu: button.track_as_menu,
+            sounds: BTreeMap::new(),
+        }
     }
 }
@@ -1,3 +1,5 @@
+use crate::avm1;
+use crate::avm2;
 use crate::bitmap::Bitmap;
 use crate::button::Button;
 use crate::shape::Shape;
@@ -22,6 +24,19 @@ pub enum Character<'a> {
 #[derive(Default)]
 pub struct Dictionary<'a> {
     pub characters: BTreeMap<CharacterId, Character<'a>>,
+
+    // Linkage names, from `ExportAssets` and `ImportAssets`.
+    pub export_names: BTreeMap<&'a str, CharacterId>,
+
+    // `DoInitAction` code, to run in the frame containing the tag
+    // (see `Timeline::init_sprites`).
+    pub init_actions: BTreeMap<CharacterId, avm1::Code>,
+
+    // AS3 classes linked to characters (or, for ID `0`, the main timeline),
+    // from `SymbolClass`, and the ABC files (from `DoABC`, with their names)
+    // defining them.
+    pub symbol_classes: BTreeMap<CharacterId, &'a str>,
+    pub abc: Vec<(&'a str, avm2::Abc<'a>)>,
 }
 
 impl<'a> Dictionary<'a> {
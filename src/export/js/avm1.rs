@@ -14,46 +14,239 @@ impl avm1::Value {
             avm1::Value::Str(s) => js::string(s),
 
             avm1::Value::OpRes(i) => js::code! { "_", i },
+            avm1::Value::Op(op) => js::code! { "(", op.to_js_value().unwrap(), ")" },
         }
     }
 }
 
+impl avm1::SendVars {
+    fn to_js(self) -> js::Code {
+        match self {
+            avm1::SendVars::None => js::code! { "null" },
+            avm1::SendVars::Get => js::string("GET"),
+            avm1::SendVars::Post => js::string("POST"),
+        }
+    }
+}
+
+impl avm1::BinOp {
+    // The JS operator, and the conversion applied to both operands first.
+    fn to_js(self) -> (&'static str, &'static str) {
+        match self {
+            avm1::BinOp::Add => ("+", ""),
+            avm1::BinOp::Sub => ("-", ""),
+            avm1::BinOp::Mul => ("*", ""),
+            avm1::BinOp::Div => ("/", ""),
+            avm1::BinOp::Mod => ("%", ""),
+            avm1::BinOp::Eq => ("==", ""),
+            avm1::BinOp::StrictEq => ("===", ""),
+            avm1::BinOp::Lt => ("<", ""),
+            avm1::BinOp::Gt => (">", ""),
+            avm1::BinOp::BitAnd => ("&", ""),
+            avm1::BinOp::BitOr => ("|", ""),
+            avm1::BinOp::BitXor => ("^", ""),
+            avm1::BinOp::Shl => ("<<", ""),
+            avm1::BinOp::Shr => (">>", ""),
+            avm1::BinOp::UShr => (">>>", ""),
+            avm1::BinOp::NumAdd => ("+", "+"),
+            avm1::BinOp::NumEq => ("==", "+"),
+            avm1::BinOp::NumLt => ("<", "+"),
+            avm1::BinOp::StrAdd => ("+", "\"\" + "),
+            avm1::BinOp::StrEq => ("==", "\"\" + "),
+            avm1::BinOp::StrLt => ("<", "\"\" + "),
+            avm1::BinOp::StrGt => (">", "\"\" + "),
+        }
+    }
+}
+
+impl avm1::UnOp {
+    // The JS expression, around the operand.
+    fn to_js(self) -> (&'static str, &'static str) {
+        match self {
+            avm1::UnOp::Not => ("!", ""),
+            avm1::UnOp::Increment => ("+", " + 1"),
+            avm1::UnOp::Decrement => ("+", " - 1"),
+            avm1::UnOp::ToNumber => ("+", ""),
+            avm1::UnOp::ToString => ("\"\" + ", ""),
+        }
+    }
+}
+
+// Whether a variable name is a target path (e.g. `/clip:var` or `_root.var`),
+// or otherwise can't be looked up directly on the current movie clip
+// (e.g. `this`, `_root`, `_parent` or `_level0` on their own).
+fn needs_resolving(name: &str) -> bool {
+    let is_level = name.starts_with("_level")
+        && name.len() > "_level".len()
+        && name["_level".len()..].bytes().all(|b| b.is_ascii_digit());
+    ["this", "_root", "_parent"].contains(&name) || is_level || name.contains(&[':', '/', '.'][..])
+}
+
+fn comma_separated(items: impl IntoIterator<Item = js::Code>) -> js::Code {
+    let mut code = js::code! {};
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            code += js::code! { ", " };
+        }
+        code += item;
+    }
+    code
+}
+
+// NB: `target` starts out as `local.this`, but `SetTarget` can change it.
+fn this_call(name: &str, args: impl IntoIterator<Item = js::Code>) -> js::Code {
+    js::call(js::code! { "target.", name }, args)
+}
+
+impl avm1::Op {
+    // The JS expression for the result of an op, if it has one.
+    fn to_js_value(&self) -> Option<js::Code> {
+        Some(match self {
+            avm1::Op::GetVar(name) if needs_resolving(name) => {
+                this_call("getVariable", vec![js::string(name)])
+            }
+            avm1::Op::GetVar(name) => this_call("lookupVariable", vec![js::string(name)]),
+            avm1::Op::GetProperty(target, prop) => {
+                this_call("getProperty", vec![target.to_js(), js::string(prop.name())])
+            }
+            avm1::Op::GetMember(object, name) => js::code! {
+                object.to_js(), "[", name.to_js(), "]"
+            },
+            avm1::Op::Binary(op, a, b) => {
+                let (op, convert) = op.to_js();
+                js::code! { convert, a.to_js(), " ", op, " ", convert, b.to_js() }
+            }
+            avm1::Op::Unary(op, value) => {
+                let (prefix, suffix) = op.to_js();
+                js::code! { prefix, value.to_js(), suffix }
+            }
+            // NB: array literals need the prototype of Flash's `Array`.
+            avm1::Op::InitArray(elems) => js::code! {
+                "Object.setPrototypeOf([",
+                comma_separated(elems.iter().map(|elem| elem.to_js())),
+                "], global.Array.prototype)"
+            },
+            avm1::Op::InitObject(props) => js::code! {
+                "{",
+                comma_separated(
+                    props
+                        .iter()
+                        .map(|(name, value)| js::code! { js::string(name), ": ", value.to_js() }),
+                ),
+                "}"
+            },
+            avm1::Op::Call(callee, args) => {
+                js::call(callee.to_js(), args.iter().map(|arg| arg.to_js()))
+            }
+            avm1::Op::New(callee, args) => js::call(
+                js::code! { "new ", callee.to_js() },
+                args.iter().map(|arg| arg.to_js()),
+            ),
+            avm1::Op::CallMethod(receiver, name, args) => js::call(
+                js::code! { receiver.to_js(), ".", name },
+                args.iter().map(|arg| arg.to_js()),
+            ),
+            _ => return None,
+        })
+    }
+
+    fn to_js(&self) -> js::Code {
+        match self {
+            avm1::Op::Play => this_call("play", vec![]),
+            avm1::Op::Stop => this_call("stop", vec![]),
+            avm1::Op::NextFrame => this_call("nextFrame", vec![]),
+            avm1::Op::PrevFrame => this_call("prevFrame", vec![]),
+            avm1::Op::GotoFrame(frame) => this_call("goto", vec![js::code! { frame.0 }]),
+            avm1::Op::GotoLabel(name) => this_call("goto", vec![js::string(name)]),
+            avm1::Op::GotoFrame2(frame, play, scene_bias) => this_call(
+                "gotoFrame2",
+                vec![
+                    frame.to_js(),
+                    js::code! { play },
+                    js::code! { scene_bias.0 },
+                ],
+            ),
+            avm1::Op::GetUrl(url, target) => {
+                this_call("getURL", vec![js::string(url), js::string(target)])
+            }
+            avm1::Op::GetUrl2(url, target, method) => {
+                this_call("getURL", vec![url.to_js(), target.to_js(), method.to_js()])
+            }
+            avm1::Op::LoadVariables(url, target, method) => this_call(
+                "loadVariables",
+                vec![url.to_js(), target.to_js(), method.to_js()],
+            ),
+            avm1::Op::LoadMovie(url, target, method) => this_call(
+                "loadMovieTarget",
+                vec![url.to_js(), target.to_js(), method.to_js()],
+            ),
+
+            avm1::Op::SetTarget(target) => js::code! {
+                "target = local.this.tellTarget(", target.to_js(), ")"
+            },
+            avm1::Op::StopSounds => this_call("stopAllSounds", vec![]),
+            avm1::Op::ToggleQuality => this_call("toggleHighQuality", vec![]),
+            avm1::Op::Trace(value) => this_call("trace", vec![value.to_js()]),
+
+            avm1::Op::CloneSprite(source, name, depth) => this_call(
+                "cloneSprite",
+                vec![source.to_js(), name.to_js(), depth.to_js()],
+            ),
+            avm1::Op::RemoveSprite(target) => this_call("removeSprite", vec![target.to_js()]),
+
+            avm1::Op::SetVar(name, value) => {
+                this_call("setVariable", vec![js::string(name), value.to_js()])
+            }
+            avm1::Op::SetProperty(target, prop, value) => this_call(
+                "setProperty",
+                vec![target.to_js(), js::string(prop.name()), value.to_js()],
+            ),
+            avm1::Op::SetMember(object, name, value) => this_call(
+                "setMember",
+                vec![object.to_js(), name.to_js(), value.to_js()],
+            ),
+
+            _ => self.to_js_value().unwrap(),
+        }
+    }
+}
+
+fn mark_used(value: &avm1::Value, used: &mut [bool]) {
+    match value {
+        avm1::Value::OpRes(i) => used[*i] = true,
+        avm1::Value::Op(op) => {
+            for value in op.values() {
+                mark_used(value, used);
+            }
+        }
+        _ => {}
+    }
+}
+
 pub fn export<'a>(codes: impl IntoIterator<Item = &'a avm1::Code>) -> js::Code {
     let mut js_body = js::code! {};
 
-    fn this_call(name: &str, args: impl IntoIterator<Item = js::Code>) -> js::Code {
-        js::call(js::code! { "local.this.", name }, args)
-    }
+    js_body += js::code! { "\nvar target;" };
 
     for code in codes {
+        // Each `DoAction` starts out targeting its own movie clip again,
+        // even if a previous one in the same frame used `SetTarget`.
+        js_body += js::code! { "\ntarget = local.this;" };
+
+        // Only results which are used need to be kept in variables.
+        let mut used = vec![false; code.ops.len()];
+        for op in &code.ops {
+            for value in op.values() {
+                mark_used(value, &mut used);
+            }
+        }
+
         for (i, op) in code.ops.iter().enumerate() {
-            let assign = |value| js::code! { "var _", i, " = ", value };
             js_body += js::code! { "\n" };
-            js_body += match op {
-                avm1::Op::Play => this_call("play", vec![]),
-                avm1::Op::Stop => this_call("stop", vec![]),
-                avm1::Op::GotoFrame(frame) => this_call("goto", vec![js::code! { frame.0 }]),
-                avm1::Op::GotoLabel(name) => this_call("goto", vec![js::string(name)]),
-                avm1::Op::GetUrl(url, target) => {
-                    this_call("getURL", vec![js::string(url), js::string(target)])
-                }
-
-                avm1::Op::GetVar(name) => assign(js::code! {
-                    "(", js::string(name), " in local) ? ",
-                    "local[", js::string(name), "] : ",
-                    "global[", js::string(name), "]"
-                }),
-                avm1::Op::SetVar(name, value) => js::code! {
-                    "local[", js::string(name), "] = ", value.to_js()
-                },
-
-                avm1::Op::Call(callee, args) => {
-                    assign(js::call(callee.to_js(), args.iter().map(|arg| arg.to_js())))
-                }
-                avm1::Op::CallMethod(receiver, name, args) => assign(js::call(
-                    js::code! { receiver.to_js(), ".", name },
-                    args.iter().map(|arg| arg.to_js()),
-                )),
+            js_body += match op.to_js_value() {
+                Some(value) if used[i] => js::code! { "var _", i, " = ", value },
+                Some(value) => value,
+                None => op.to_js(),
             };
             js_body += js::code! { ";" };
         }
@@ -61,3 +254,423 @@ pub fn export<'a>(codes: impl IntoIterator<Item = &'a avm1::Code>) -> js::Code {
 
     js::code! { "function(global, local) {", js_body.indent(), "\n}" }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::avm1;
+
+    // Just enough of an AVM1 assembler to write actions by hand.
+    enum Push<'a> {
+        Str(&'a str),
+        F32(f32),
+        I32(i32),
+    }
+
+    fn push(values: &[Push]) -> Vec<u8> {
+        let mut data = vec![];
+        for value in values {
+            match *value {
+                Push::Str(s) => {
+                    data.push(0);
+                    data.extend(s.bytes());
+                    data.push(0);
+                }
+                Push::F32(x) => {
+                    data.push(1);
+                    data.extend(&x.to_le_bytes());
+                }
+                Push::I32(x) => {
+                    data.push(7);
+                    data.extend(&x.to_le_bytes());
+                }
+            }
+        }
+        let mut action = vec![0x96];
+        action.extend(&(data.len() as u16).to_le_bytes());
+        action.extend(data);
+        action
+    }
+
+    const GET_VARIABLE: u8 = 0x1c;
+    const SET_VARIABLE: u8 = 0x1d;
+    const TO_INTEGER: u8 = 0x18;
+    const RANDOM_NUMBER: u8 = 0x30;
+    const GET_TIME: u8 = 0x34;
+    const CALL_FUNCTION: u8 = 0x3d;
+    const NEW_OBJECT: u8 = 0x40;
+    const INIT_ARRAY: u8 = 0x42;
+    const INIT_OBJECT: u8 = 0x43;
+    const ADD2: u8 = 0x47;
+    const LESS2: u8 = 0x48;
+    const MULTIPLY: u8 = 0x0c;
+    const NOT: u8 = 0x12;
+    const STRING_ADD: u8 = 0x21;
+    const INCREMENT: u8 = 0x50;
+    const GET_MEMBER: u8 = 0x4e;
+    const SET_MEMBER: u8 = 0x4f;
+    const CALL_METHOD: u8 = 0x52;
+
+    // Compile actions (which must compile fully) and export them to JS,
+    // returning the statements (one per line, without the `target` setup).
+    fn compile(parts: &[&[u8]]) -> Vec<String> {
+        let mut data = parts.concat();
+        data.push(0);
+        let code = avm1::Code::parse_and_compile(&data);
+        assert_eq!(code.bailed, None);
+        let js = super::export(Some(&code)).0;
+        js.lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| {
+                !line.starts_with("function")
+                    && line != "}"
+                    && line != "var target;"
+                    && line != "target = local.this;"
+            })
+            .collect()
+    }
+
+    // `global[name]`, unless the current movie clip has a variable `name`.
+    fn global(name: &str) -> String {
+        format!("target.lookupVariable(\"{}\")", name)
+    }
+
+    #[test]
+    fn int() {
+        // `x = int(3.7)`, via the `ToInteger` action.
+        assert_eq!(
+            compile(&[
+                &push(&[Push::Str("x"), Push::F32(3.7)]),
+                &[TO_INTEGER],
+                &[SET_VARIABLE],
+            ]),
+            ["target.setVariable(\"x\", 3);"]
+        );
+
+        // `x = int(3.7)`, via a call to the global function.
+        assert_eq!(
+            compile(&[
+                &push(&[
+                    Push::Str("x"),
+                    Push::F32(3.7),
+                    Push::I32(1),
+                    Push::Str("int")
+                ]),
+                &[CALL_FUNCTION],
+                &[SET_VARIABLE],
+            ]),
+            ["target.setVariable(\"x\", 3);"]
+        );
+    }
+
+    #[test]
+    fn random_and_get_timer() {
+        // `x = random(10); t = getTimer();`
+        assert_eq!(
+            compile(&[
+                &push(&[Push::Str("x"), Push::I32(10)]),
+                &[RANDOM_NUMBER],
+                &[SET_VARIABLE],
+                &push(&[Push::Str("t")]),
+                &[GET_TIME],
+                &[SET_VARIABLE],
+            ]),
+            [
+                format!("target.setVariable(\"x\", (({})(10)));", global("random")),
+                format!("target.setVariable(\"t\", (({})()));", global("getTimer")),
+            ]
+        );
+    }
+
+    #[test]
+    fn key_is_down() {
+        // `left = Key.isDown(Key.LEFT)`
+        assert_eq!(
+            compile(&[
+                &push(&[Push::Str("left"), Push::Str("Key")]),
+                &[GET_VARIABLE],
+                &push(&[Push::Str("LEFT")]),
+                &[GET_MEMBER],
+                &push(&[Push::I32(1), Push::Str("Key")]),
+                &[GET_VARIABLE],
+                &push(&[Push::Str("isDown")]),
+                &[CALL_METHOD],
+                &[SET_VARIABLE],
+            ]),
+            [format!(
+                "target.setVariable(\"left\", (({1}).isDown((({0})[\"LEFT\"]))));",
+                global("Key"),
+                global("Key"),
+            )]
+        );
+    }
+
+    #[test]
+    fn array_methods() {
+        // `a = [3, 1, 2]; a.sort(Array.NUMERIC); s = a.join(",");`
+        assert_eq!(
+            compile(&[
+                &push(&[
+                    Push::Str("a"),
+                    Push::I32(2),
+                    Push::I32(1),
+                    Push::I32(3),
+                    Push::I32(3),
+                ]),
+                &[INIT_ARRAY],
+                &[SET_VARIABLE],
+                &push(&[Push::Str("Array")]),
+                &[GET_VARIABLE],
+                &push(&[Push::Str("NUMERIC")]),
+                &[GET_MEMBER],
+                &push(&[Push::I32(1), Push::Str("a")]),
+                &[GET_VARIABLE],
+                &push(&[Push::Str("sort")]),
+                &[CALL_METHOD],
+                &[0x17],
+                &push(&[Push::Str("s"), Push::Str(","), Push::I32(1), Push::Str("a")]),
+                &[GET_VARIABLE],
+                &push(&[Push::Str("join")]),
+                &[CALL_METHOD],
+                &[SET_VARIABLE],
+            ]),
+            [
+                "target.setVariable(\"a\", (Object.setPrototypeOf([3, 1, 2], global.Array.prototype)));"
+                    .to_string(),
+                format!("({}).sort((({})[\"NUMERIC\"]));", global("a"), global("Array")),
+                format!("target.setVariable(\"s\", (({}).join(\",\")));", global("a")),
+            ]
+        );
+    }
+
+    #[test]
+    fn new_array_and_object() {
+        // `a = new Array(5); o = {x: 1, y: "two"};`
+        assert_eq!(
+            compile(&[
+                &push(&[
+                    Push::Str("a"),
+                    Push::I32(5),
+                    Push::I32(1),
+                    Push::Str("Array")
+                ]),
+                &[NEW_OBJECT],
+                &[SET_VARIABLE],
+                &push(&[
+                    Push::Str("o"),
+                    Push::Str("x"),
+                    Push::I32(1),
+                    Push::Str("y"),
+                    Push::Str("two"),
+                    Push::I32(2),
+                ]),
+                &[INIT_OBJECT],
+                &[SET_VARIABLE],
+            ]),
+            [
+                format!("target.setVariable(\"a\", (new ({})(5)));", global("Array")),
+                "target.setVariable(\"o\", ({\"x\": 1, \"y\": \"two\"}));".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn operators() {
+        // `x = !(a + 1 < b * 2); s = "n" add n; n++;`
+        assert_eq!(
+            compile(&[
+                &push(&[Push::Str("x"), Push::Str("a")]),
+                &[GET_VARIABLE],
+                &push(&[Push::I32(1)]),
+                &[ADD2],
+                &push(&[Push::Str("b")]),
+                &[GET_VARIABLE],
+                &push(&[Push::I32(2)]),
+                &[MULTIPLY],
+                &[LESS2],
+                &[NOT],
+                &[SET_VARIABLE],
+                &push(&[Push::Str("s"), Push::Str("n"), Push::Str("n")]),
+                &[GET_VARIABLE],
+                &[STRING_ADD],
+                &[SET_VARIABLE],
+                &push(&[Push::Str("n"), Push::Str("n")]),
+                &[GET_VARIABLE],
+                &[INCREMENT],
+                &[SET_VARIABLE],
+            ]),
+            [
+                format!(
+                    "target.setVariable(\"x\", (!((({}) + 1) < (({}) * 2))));",
+                    global("a"),
+                    global("b"),
+                ),
+                format!(
+                    "target.setVariable(\"s\", (\"\" + \"n\" + \"\" + ({})));",
+                    global("n")
+                ),
+                format!("target.setVariable(\"n\", (+({}) + 1));", global("n")),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_member_on_clip() {
+        // `_root.score = 5; x = _root.score;`
+        assert_eq!(
+            compile(&[
+                &push(&[Push::Str("_root")]),
+                &[GET_VARIABLE],
+                &push(&[Push::Str("score"), Push::I32(5)]),
+                &[SET_MEMBER],
+                &push(&[Push::Str("x"), Push::Str("_root")]),
+                &[GET_VARIABLE],
+                &push(&[Push::Str("score")]),
+                &[GET_MEMBER],
+                &[SET_VARIABLE],
+            ]),
+            [
+                "target.setMember((target.getVariable(\"_root\")), \"score\", 5);",
+                "target.setVariable(\"x\", ((target.getVariable(\"_root\"))[\"score\"]));",
+            ]
+        );
+
+        // Movie clips are frozen, so the runtime has to write their
+        // variables to the timeline, for `GetMember` to find them.
+        assert!(RUNTIME.contains("def('setMember', rt.setMember);"));
+        assert!(RUNTIME.contains("timeline.vars[name] = value;"));
+    }
+
+    const RUNTIME: &str = include_str!("../svg/runtime.js");
+
+    // The names `rt.mkGlobalScope` defines, according to the runtime's source.
+    fn runtime_globals() -> Vec<&'static str> {
+        let start = RUNTIME.find("rt.mkGlobalScope = function").unwrap();
+        let end = start + RUNTIME[start..].find("\n    };").unwrap();
+        RUNTIME[start..end]
+            .split("def('")
+            .skip(1)
+            .map(|def| &def[..def.find('\'').unwrap()])
+            .collect()
+    }
+
+    #[test]
+    fn globals() {
+        let runtime_globals = runtime_globals();
+
+        // `x = Math.max(1, 2)`, and so on, for each built-in object, with
+        // the methods the runtime itself has to define (i.e. not from JS).
+        for &(object, method, from_runtime) in &[
+            ("Math", "max", false),
+            ("String", "fromCharCode", true),
+            ("Date", "UTC", false),
+            ("Number", "isNaN", false),
+            ("Key", "isDown", true),
+            ("Mouse", "hide", true),
+            ("SharedObject", "getLocal", true),
+        ] {
+            assert!(
+                runtime_globals.contains(&object),
+                "{} isn't defined",
+                object
+            );
+            if from_runtime {
+                assert!(
+                    RUNTIME.contains(&format!("{}: function", method))
+                        || RUNTIME.contains(&format!(".{} = ", method)),
+                    "{}.{} isn't defined",
+                    object,
+                    method
+                );
+            }
+            assert_eq!(
+                compile(&[
+                    &push(&[Push::Str("x"), Push::I32(2), Push::I32(1), Push::I32(2)]),
+                    &push(&[Push::Str(object)]),
+                    &[GET_VARIABLE],
+                    &push(&[Push::Str(method)]),
+                    &[CALL_METHOD],
+                    &[SET_VARIABLE],
+                ]),
+                [format!(
+                    "target.setVariable(\"x\", (({}).{}(1, 2)));",
+                    global(object),
+                    method
+                )]
+            );
+        }
+
+        // `x = isNaN(1)`, and so on, for each built-in function.
+        for &function in &[
+            "isNaN",
+            "isFinite",
+            "parseInt",
+            "parseFloat",
+            "escape",
+            "unescape",
+            "String",
+            "Number",
+            "Boolean",
+            "setInterval",
+            "clearInterval",
+            "setTimeout",
+            "clearTimeout",
+        ] {
+            assert!(
+                runtime_globals.contains(&function),
+                "{} isn't defined",
+                function
+            );
+            assert_eq!(
+                compile(&[
+                    &push(&[Push::Str("x"), Push::I32(1), Push::I32(1)]),
+                    &push(&[Push::Str(function)]),
+                    &[CALL_FUNCTION],
+                    &[SET_VARIABLE],
+                ]),
+                [format!(
+                    "target.setVariable(\"x\", (({})(1)));",
+                    global(function)
+                )]
+            );
+        }
+
+        // `s = new Sound(); v = new LoadVars(); o = new Object();`
+        for &class in &["Sound", "LoadVars", "Object"] {
+            assert!(runtime_globals.contains(&class), "{} isn't defined", class);
+            assert_eq!(
+                compile(&[
+                    &push(&[Push::Str("x"), Push::I32(0), Push::Str(class)]),
+                    &[NEW_OBJECT],
+                    &[SET_VARIABLE],
+                ]),
+                [format!(
+                    "target.setVariable(\"x\", (new ({})()));",
+                    global(class)
+                )]
+            );
+        }
+
+        // `_global.score = NaN + Infinity`
+        for &name in &["_global", "NaN", "Infinity"] {
+            assert!(runtime_globals.contains(&name), "{} isn't defined", name);
+        }
+        assert_eq!(
+            compile(&[
+                &push(&[Push::Str("_global")]),
+                &[GET_VARIABLE],
+                &push(&[Push::Str("score"), Push::Str("NaN")]),
+                &[GET_VARIABLE],
+                &push(&[Push::Str("Infinity")]),
+                &[GET_VARIABLE],
+                &[ADD2],
+                &[SET_MEMBER],
+            ]),
+            [format!(
+                "target.setMember(({}), \"score\", (({}) + ({})));",
+                global("_global"),
+                global("NaN"),
+                global("Infinity"),
+            )]
+        );
+    }
+}
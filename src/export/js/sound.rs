@@ -1,4 +1,5 @@
 use crate::export::js;
+use swf_types as swf;
 
 // TODO(eddyb) figure out a way to avoid copying the data URL around.
 pub fn export_mp3(mp3: &[u8]) -> js::Code {
@@ -7,3 +8,18 @@ pub fn export_mp3(mp3: &[u8]) -> js::Code {
     code += js::code! { "')" };
     code
 }
+
+// Describes how a sound should be played, for both timelines and buttons.
+pub fn export_sound_info(sound_id: u16, info: &swf::SoundInfo) -> js::Code {
+    js::object(vec![
+        ("character", js::code! { sound_id }),
+        ("no_restart", js::code! { info.sync_no_multiple }),
+        (
+            "loops",
+            match info.loop_count {
+                Some(c) => js::code! { c },
+                None => js::code! { "null" },
+            },
+        ),
+    ])
+}
@@ -1,5 +1,6 @@
 use crate::export::js;
-use crate::timeline::{Depth, Frame, Timeline};
+use crate::timeline::{ClipEvent, ClipEventHandler, Depth, Frame, Timeline};
+use std::rc::Rc;
 use swf_types as swf;
 
 #[rustfmt::skip]
@@ -47,7 +48,77 @@ pub fn export_color_transform(c: &swf::ColorTransformWithAlpha) -> js::Code {
     )
 }
 
-pub fn export(timeline: &Timeline) -> js::Code {
+pub fn export_clip_actions(handlers: &[ClipEventHandler]) -> js::Code {
+    js::array(handlers.iter().map(|handler| {
+        let mut key_code = None;
+        let events = handler.on.iter().map(|&event| {
+            js::string(match event {
+                ClipEvent::KeyPress(c) => {
+                    key_code = Some(c);
+                    "keyPress"
+                }
+
+                ClipEvent::Initialize => "initialize",
+                ClipEvent::Construct => "construct",
+                ClipEvent::Load => "load",
+                ClipEvent::Unload => "unload",
+                ClipEvent::EnterFrame => "enterFrame",
+                ClipEvent::Data => "data",
+                ClipEvent::KeyDown => "keyDown",
+                ClipEvent::KeyUp => "keyUp",
+                ClipEvent::MouseDown => "mouseDown",
+                ClipEvent::MouseUp => "mouseUp",
+                ClipEvent::MouseMove => "mouseMove",
+                ClipEvent::Press => "press",
+                ClipEvent::Release => "release",
+                ClipEvent::ReleaseOutside => "releaseOutside",
+                ClipEvent::RollOver => "rollOver",
+                ClipEvent::RollOut => "rollOut",
+                ClipEvent::DragOver => "dragOver",
+                ClipEvent::DragOut => "dragOut",
+            })
+        });
+        let events = js::array(events);
+        js::object(vec![
+            ("events", events),
+            (
+                "key_code",
+                match key_code {
+                    Some(c) => js::code! { c },
+                    None => js::code! { "null" },
+                },
+            ),
+            ("actions", js::avm1::export(Some(&handler.actions))),
+        ])
+    }))
+}
+
+// Uses the same names as `MovieClip#blendMode`.
+fn export_blend_mode(blend_mode: swf::BlendMode) -> js::Code {
+    let name = match blend_mode {
+        swf::BlendMode::Normal => return js::code! { "null" },
+        swf::BlendMode::Layer => "layer",
+        swf::BlendMode::Multiply => "multiply",
+        swf::BlendMode::Screen => "screen",
+        swf::BlendMode::Lighten => "lighten",
+        swf::BlendMode::Darken => "darken",
+        swf::BlendMode::Difference => "difference",
+        swf::BlendMode::Add => "add",
+        swf::BlendMode::Subtract => "subtract",
+        swf::BlendMode::Invert => "invert",
+        swf::BlendMode::Alpha => "alpha",
+        swf::BlendMode::Erase => "erase",
+        swf::BlendMode::Overlay => "overlay",
+        swf::BlendMode::Hardlight => "hardlight",
+    };
+    js::string(name)
+}
+
+// `filter_id` returns the id of the `<filter>` for a list of filters, if any.
+pub fn export(
+    timeline: &Timeline,
+    filter_id: &mut dyn FnMut(&[swf::Filter]) -> Option<String>,
+) -> js::Code {
     let max_depth = timeline
         .layers
         .keys()
@@ -55,6 +126,26 @@ pub fn export(timeline: &Timeline) -> js::Code {
         .rev()
         .next()
         .unwrap_or(Depth(0));
+
+    // Objects keep their clip actions across frames, so each set of them
+    // is only exported once, with frames referring to it by index.
+    let mut clip_actions: Vec<&Rc<Vec<ClipEventHandler>>> = vec![];
+    for layer in timeline.layers.values() {
+        for obj in layer.frames.values().flatten() {
+            if let Some(handlers) = &obj.clip_actions {
+                if !clip_actions.iter().any(|other| Rc::ptr_eq(other, handlers)) {
+                    clip_actions.push(handlers);
+                }
+            }
+        }
+    }
+    let clip_actions_index = |handlers: &Rc<Vec<ClipEventHandler>>| {
+        clip_actions
+            .iter()
+            .position(|other| Rc::ptr_eq(other, handlers))
+            .unwrap()
+    };
+
     js::object(vec![
         (
             "layers",
@@ -91,6 +182,42 @@ pub fn export(timeline: &Timeline) -> js::Code {
                                             None => js::code! { "null" },
                                         },
                                     ),
+                                    (
+                                        "filters",
+                                        match filter_id(&obj.filters) {
+                                            Some(id) => js::string(&id),
+                                            None => js::code! { "null" },
+                                        },
+                                    ),
+                                    ("blend_mode", export_blend_mode(obj.blend_mode)),
+                                    ("visible", js::code! { obj.visible }),
+                                    ("cache_as_bitmap", js::code! { obj.cache_as_bitmap }),
+                                    (
+                                        "background_color",
+                                        match obj.background_color {
+                                            Some(c) => js::string(&format!(
+                                                "#{:02x}{:02x}{:02x}",
+                                                c.r, c.g, c.b
+                                            )),
+                                            None => js::code! { "null" },
+                                        },
+                                    ),
+                                    (
+                                        "clip_depth",
+                                        match obj.clip_depth {
+                                            Some(depth) => js::code! { depth.0 },
+                                            None => js::code! { "null" },
+                                        },
+                                    ),
+                                    (
+                                        "clip_actions",
+                                        match &obj.clip_actions {
+                                            Some(handlers) => {
+                                                js::code! { clip_actions_index(handlers) }
+                                            }
+                                            None => js::code! { "null" },
+                                        },
+                                    ),
                                 ]),
                                 Some(None) => js::code! { "null" },
                                 None => js::code! {},
@@ -101,6 +228,14 @@ pub fn export(timeline: &Timeline) -> js::Code {
                 },
             )),
         ),
+        (
+            "clip_actions",
+            js::array(
+                clip_actions
+                    .iter()
+                    .map(|handlers| export_clip_actions(handlers)),
+            ),
+        ),
         ("actions", {
             let last_frame = timeline
                 .actions
@@ -116,6 +251,20 @@ pub fn export(timeline: &Timeline) -> js::Code {
                 },
             ))
         }),
+        ("init_sprites", {
+            let last_frame = timeline
+                .init_sprites
+                .keys()
+                .next_back()
+                .cloned()
+                .unwrap_or(Frame(0));
+            js::array((0..=last_frame.0).map(Frame).map(|frame| {
+                match timeline.init_sprites.get(&frame) {
+                    Some(sprites) => js::array(sprites.iter().map(|id| js::code! { id.0 })),
+                    None => js::code! {},
+                }
+            }))
+        }),
         (
             "labels",
             js::object(
@@ -136,20 +285,7 @@ pub fn export(timeline: &Timeline) -> js::Code {
             js::array((0..=last_frame.0).map(Frame).map(
                 |frame| match timeline.sounds.get(&frame) {
                     Some(sounds) => js::array(sounds.iter().map(|sound| {
-                        js::object(vec![
-                            ("character", js::code! { sound.sound_id }),
-                            (
-                                "no_restart",
-                                js::code! { sound.sound_info.sync_no_multiple },
-                            ),
-                            (
-                                "loops",
-                                match sound.sound_info.loop_count {
-                                    Some(c) => js::code! { c },
-                                    None => js::code! { "null" },
-                                },
-                            ),
-                        ])
+                        js::sound::export_sound_info(sound.sound_id, &sound.sound_info)
                     })),
                     None => js::code! {},
                 },
@@ -166,5 +302,14 @@ pub fn export(timeline: &Timeline) -> js::Code {
             },
         ),
         ("frame_count", js::code! { timeline.frame_count.0 }),
+        (
+            "scenes",
+            js::array(timeline.scenes.iter().map(|(frame, name)| {
+                js::object(vec![
+                    ("name", js::string(name)),
+                    ("offset", js::code! { frame.0 }),
+                ])
+            })),
+        ),
     ])
 }
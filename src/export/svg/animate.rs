@@ -15,7 +15,7 @@ struct Animation<T> {
     current_value: T,
 }
 
-impl<T: Copy + PartialEq + Into<svg::node::Value>> Animation<T> {
+impl<T: Clone + PartialEq + Into<svg::node::Value>> Animation<T> {
     fn new(frame_count: Frame, movie_duration: f64, initial_value: T) -> Self {
         Animation {
             frame_count,
@@ -31,7 +31,7 @@ impl<T: Copy + PartialEq + Into<svg::node::Value>> Animation<T> {
             return;
         }
         if frame != Frame(0) && self.key_times.is_empty() {
-            self.add_without_checking(Frame(0), self.current_value);
+            self.add_without_checking(Frame(0), self.current_value.clone());
         }
         self.add_without_checking(frame, value);
     }
@@ -43,7 +43,11 @@ impl<T: Copy + PartialEq + Into<svg::node::Value>> Animation<T> {
             self.values.push(';');
         }
         let _ = write!(self.key_times, "{}", t);
-        let _ = write!(self.values, "{}", Into::<svg::node::Value>::into(value));
+        let _ = write!(
+            self.values,
+            "{}",
+            Into::<svg::node::Value>::into(value.clone())
+        );
         self.current_value = value;
     }
 
@@ -64,6 +68,25 @@ impl<T: Copy + PartialEq + Into<svg::node::Value>> Animation<T> {
         node
     }
 
+    // Like `animate`, but for CSS properties without an equivalent attribute.
+    fn animate_style<U: Node>(self, mut node: U, property: &str) -> U {
+        match &self.key_times[..] {
+            "" => {}
+            "0" => node.assign("style", format!("{}: {}", property, self.values)),
+            _ => node.append(
+                Animate::new()
+                    .set("attributeName", property)
+                    .set("attributeType", "CSS")
+                    .set("keyTimes", self.key_times)
+                    .set("values", self.values)
+                    .set("calcMode", "discrete")
+                    .set("repeatCount", "indefinite")
+                    .set("dur", self.movie_duration),
+            ),
+        }
+        node
+    }
+
     fn animate_transform(self, g: Group, ty: &str) -> Group {
         match &self.key_times[..] {
             "" => g,
@@ -180,6 +203,29 @@ impl Into<svg::node::Value> for ColorMatrix {
     }
 }
 
+fn css_blend_mode(blend_mode: swf::BlendMode) -> &'static str {
+    match blend_mode {
+        swf::BlendMode::Multiply => "multiply",
+        swf::BlendMode::Screen => "screen",
+        swf::BlendMode::Lighten => "lighten",
+        swf::BlendMode::Darken => "darken",
+        swf::BlendMode::Difference => "difference",
+        swf::BlendMode::Overlay => "overlay",
+        swf::BlendMode::Hardlight => "hard-light",
+        // HACK these don't have exact CSS equivalents, so
+        // the closest ones are used instead (`plus-lighter` clamps
+        // the sum, like Flash, but not all browsers support it).
+        swf::BlendMode::Add => "plus-lighter",
+        swf::BlendMode::Subtract | swf::BlendMode::Invert => "difference",
+        // NB: `Layer` only isolates the object, while `Alpha` and `Erase`
+        // are masks for what's below them (see `export_timeline`).
+        swf::BlendMode::Normal
+        | swf::BlendMode::Layer
+        | swf::BlendMode::Alpha
+        | swf::BlendMode::Erase => "normal",
+    }
+}
+
 #[derive(Copy, Clone, PartialEq)]
 struct CharacterUseHref(Option<CharacterId>);
 
@@ -192,6 +238,22 @@ impl Into<svg::node::Value> for CharacterUseHref {
     }
 }
 
+// Hide `node` during the frames in `start..end`.
+pub fn hide_between<U: Node>(
+    node: U,
+    frame_count: Frame,
+    movie_duration: f64,
+    start: Frame,
+    end: Frame,
+) -> U {
+    let mut display = Animation::new(frame_count, movie_duration, "inline");
+    display.add(start, "none");
+    if end < frame_count {
+        display.add(end, "inline");
+    }
+    display.animate(node, "display")
+}
+
 pub struct ObjectAnimation {
     id_prefix: String,
 
@@ -203,6 +265,12 @@ pub struct ObjectAnimation {
     translate: Animation<(i32, i32)>,
 
     color_matrix: Animation<ColorMatrix>,
+    background_color: Animation<String>,
+
+    filter: Animation<String>,
+    blend_mode: Animation<&'static str>,
+    isolate: bool,
+    display: Animation<&'static str>,
 }
 
 impl ObjectAnimation {
@@ -225,10 +293,23 @@ impl ObjectAnimation {
                     add: [0; 4],
                 },
             ),
+
+            background_color: Animation::new(
+                frame_count,
+                movie_duration,
+                "transparent".to_string(),
+            ),
+
+            filter: Animation::new(frame_count, movie_duration, "none".to_string()),
+            blend_mode: Animation::new(frame_count, movie_duration, "normal"),
+            isolate: false,
+            display: Animation::new(frame_count, movie_duration, "inline"),
         }
     }
 
-    pub fn add(&mut self, frame: Frame, obj: Option<&Object>) {
+    // `filter_id` refers to the `<filter>` for `obj.filters`, if any,
+    // which is shared between all the objects with the same filters.
+    pub fn add(&mut self, frame: Frame, obj: Option<&Object>, filter_id: Option<String>) {
         let obj = match obj {
             None => {
                 self.character.add(frame, CharacterUseHref(None));
@@ -248,6 +329,26 @@ impl ObjectAnimation {
 
         self.color_matrix
             .add(frame, ColorMatrix::from(&obj.color_transform));
+        self.background_color.add(
+            frame,
+            obj.background_color.map_or("transparent".to_string(), |c| {
+                format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+            }),
+        );
+
+        self.filter.add(
+            frame,
+            filter_id.map_or("none".to_string(), |id| format!("url(#{})", id)),
+        );
+
+        // FIXME only isolate on the frames using `Layer`.
+        if obj.blend_mode == swf::BlendMode::Layer {
+            self.isolate = true;
+        }
+        self.blend_mode.add(frame, css_blend_mode(obj.blend_mode));
+
+        self.display
+            .add(frame, if obj.visible { "inline" } else { "none" });
     }
 
     pub fn to_svg(self) -> Group {
@@ -277,11 +378,55 @@ impl ObjectAnimation {
 
         g = g.add(obj);
 
+        if !self.background_color.key_times.is_empty() {
+            // The flood covers exactly the bounding box of the object,
+            // while the filter region is larger, to avoid clipping strokes.
+            let filter_id = format!("{}background", self.id_prefix);
+            let mut merge = Element::new("feMerge");
+            for input in ["flood", "SourceGraphic"] {
+                let mut node = Element::new("feMergeNode");
+                node.assign("in", input);
+                merge.append(node);
+            }
+            let mut flood = Element::new("feFlood");
+            flood.assign("x", 0);
+            flood.assign("y", 0);
+            flood.assign("width", 1);
+            flood.assign("height", 1);
+            flood.assign("result", "flood");
+            g = Group::new()
+                .add(
+                    Filter::new()
+                        .set("id", filter_id.clone())
+                        .set("x", "-50%")
+                        .set("y", "-50%")
+                        .set("width", "200%")
+                        .set("height", "200%")
+                        .set("primitiveUnits", "objectBoundingBox")
+                        .add(self.background_color.animate(flood, "flood-color"))
+                        .add(merge),
+                )
+                .add(g.set("filter", format!("url(#{})", filter_id)));
+        }
+
         g = self.scale.animate_transform(g, "scale");
         g = self.skew_y.animate_transform(g, "skewY");
         g = self.rotate.animate_transform(g, "rotate");
         g = self.translate.animate_transform(g, "translate");
 
+        // Blending inside the object can't affect anything outside it.
+        if self.isolate {
+            g = Group::new().add(g).set("style", "isolation: isolate");
+        }
+
+        // Filters and blending apply after the object's own transform.
+        if !self.filter.key_times.is_empty() || !self.blend_mode.key_times.is_empty() {
+            let outer = self.filter.animate(Group::new().add(g), "filter");
+            g = self.blend_mode.animate_style(outer, "mix-blend-mode");
+        }
+
+        g = self.display.animate(g, "display");
+
         g
     }
 }
@@ -0,0 +1,450 @@
+use svg::node::element::{Element, Filter};
+use svg::node::Value;
+use svg::Node;
+use swf_types as swf;
+
+// Filter parameters are in pixels, but SVG coordinates are in twips.
+const TWIPS_PER_PIXEL: f64 = 20.0;
+
+// Builds a graph of filter primitives, each with its own result.
+struct Chain {
+    filter: Filter,
+    results: usize,
+}
+
+impl Chain {
+    fn add(&mut self, mut primitive: Element) -> String {
+        let result = format!("r{}", self.results);
+        self.results += 1;
+        primitive.assign("result", result.clone());
+        self.filter.append(primitive);
+        result
+    }
+}
+
+fn primitive(name: &str, attrs: Vec<(&str, Value)>) -> Element {
+    let mut element = Element::new(name);
+    for (name, value) in attrs {
+        element.assign(name, value);
+    }
+    element
+}
+
+// Flash blurs by applying a box blur `passes` times, which can be
+// approximated by a gaussian blur with the same variance.
+fn std_deviation(
+    blur_x: swf::fixed::Sfixed16P16,
+    blur_y: swf::fixed::Sfixed16P16,
+    passes: u8,
+) -> String {
+    let scale = (f64::from(passes) / 12.0).sqrt() * TWIPS_PER_PIXEL;
+    format!(
+        "{} {}",
+        f64::from(blur_x) * scale,
+        f64::from(blur_y) * scale
+    )
+}
+
+fn offset(angle: swf::fixed::Sfixed16P16, distance: swf::fixed::Sfixed16P16) -> (f64, f64) {
+    let angle = f64::from(angle);
+    let distance = f64::from(distance) * TWIPS_PER_PIXEL;
+    (angle.cos() * distance, angle.sin() * distance)
+}
+
+fn rgb(color: swf::StraightSRgba8) -> String {
+    format!("rgb({},{},{})", color.r, color.g, color.b)
+}
+
+// What the alpha of a shadow, glow or bevel gets colored with.
+#[derive(Copy, Clone)]
+enum Paint<'a> {
+    Color(swf::StraightSRgba8),
+    // Alpha picks a color from the gradient, with `0` at its start.
+    Gradient(&'a [swf::ColorStop]),
+}
+
+// Sample a gradient into `feFuncX` table values, one for each channel.
+fn gradient_tables(stops: &[swf::ColorStop]) -> [String; 4] {
+    const SAMPLES: u32 = 17;
+    let mut tables = [String::new(), String::new(), String::new(), String::new()];
+    for i in 0..SAMPLES {
+        let ratio = (i * 255 / (SAMPLES - 1)) as u8;
+        let next = stops
+            .iter()
+            .position(|stop| stop.ratio >= ratio)
+            .unwrap_or(stops.len());
+        let color = match (next.checked_sub(1).map(|i| &stops[i]), stops.get(next)) {
+            (Some(a), Some(b)) => {
+                let t = f64::from(ratio - a.ratio) / f64::from(b.ratio - a.ratio);
+                let lerp = |x: u8, y: u8| f64::from(x) + (f64::from(y) - f64::from(x)) * t;
+                [
+                    lerp(a.color.r, b.color.r),
+                    lerp(a.color.g, b.color.g),
+                    lerp(a.color.b, b.color.b),
+                    lerp(a.color.a, b.color.a),
+                ]
+            }
+            (Some(stop), None) | (None, Some(stop)) => [
+                f64::from(stop.color.r),
+                f64::from(stop.color.g),
+                f64::from(stop.color.b),
+                f64::from(stop.color.a),
+            ],
+            (None, None) => [0.0; 4],
+        };
+        for (table, &x) in tables.iter_mut().zip(&color) {
+            if !table.is_empty() {
+                table.push(' ');
+            }
+            table.push_str(&(x / 255.0).to_string());
+        }
+    }
+    tables
+}
+
+impl Chain {
+    fn blur(&mut self, input: &str, std_deviation: String) -> String {
+        self.add(primitive(
+            "feGaussianBlur",
+            vec![("in", input.into()), ("stdDeviation", std_deviation.into())],
+        ))
+    }
+
+    fn offset(&mut self, input: &str, (dx, dy): (f64, f64)) -> String {
+        if (dx, dy) == (0.0, 0.0) {
+            return input.to_string();
+        }
+        self.add(primitive(
+            "feOffset",
+            vec![("in", input.into()), ("dx", dx.into()), ("dy", dy.into())],
+        ))
+    }
+
+    fn composite(&mut self, a: &str, b: &str, operator: &str) -> String {
+        self.add(primitive(
+            "feComposite",
+            vec![
+                ("in", a.into()),
+                ("in2", b.into()),
+                ("operator", operator.into()),
+            ],
+        ))
+    }
+
+    // Computes `k2 * a + k3 * b + k4`, for each (clamped) channel.
+    fn arithmetic(&mut self, a: &str, b: &str, (k2, k3, k4): (f64, f64, f64)) -> String {
+        self.add(primitive(
+            "feComposite",
+            vec![
+                ("in", a.into()),
+                ("in2", b.into()),
+                ("operator", "arithmetic".into()),
+                ("k2", k2.into()),
+                ("k3", k3.into()),
+                ("k4", k4.into()),
+            ],
+        ))
+    }
+
+    fn merge(&mut self, inputs: &[&str]) -> String {
+        let mut merge = primitive("feMerge", vec![]);
+        for &input in inputs {
+            merge.append(primitive("feMergeNode", vec![("in", input.into())]));
+        }
+        self.add(merge)
+    }
+
+    fn strengthen(&mut self, input: &str, strength: swf::fixed::Sfixed8P8) -> String {
+        let strength = f64::from(f32::from(strength));
+        if strength == 1.0 {
+            return input.to_string();
+        }
+        let mut transfer = primitive("feComponentTransfer", vec![("in", input.into())]);
+        transfer.append(primitive(
+            "feFuncA",
+            vec![("type", "linear".into()), ("slope", strength.into())],
+        ));
+        self.add(transfer)
+    }
+
+    fn paint(&mut self, alpha: &str, paint: Paint<'_>) -> String {
+        match paint {
+            Paint::Color(color) => {
+                let flood = self.add(primitive(
+                    "feFlood",
+                    vec![
+                        ("flood-color", rgb(color).into()),
+                        ("flood-opacity", (f64::from(color.a) / 255.0).into()),
+                    ],
+                ));
+                self.composite(&flood, alpha, "in")
+            }
+            Paint::Gradient(stops) => {
+                // Copy alpha into all channels, then map each through the gradient.
+                let gray = self.add(primitive(
+                    "feColorMatrix",
+                    vec![
+                        ("in", alpha.into()),
+                        ("type", "matrix".into()),
+                        ("values", "0 0 0 1 0 0 0 0 1 0 0 0 0 1 0 0 0 0 1 0".into()),
+                    ],
+                ));
+                let mut transfer = primitive("feComponentTransfer", vec![("in", gray.into())]);
+                let funcs = ["feFuncR", "feFuncG", "feFuncB", "feFuncA"];
+                for (func, table) in funcs.iter().zip(gradient_tables(stops).iter()) {
+                    transfer.append(primitive(
+                        func,
+                        vec![
+                            ("type", "table".into()),
+                            ("tableValues", table.clone().into()),
+                        ],
+                    ));
+                }
+                self.add(transfer)
+            }
+        }
+    }
+
+    // Combine the result of a shadow-like filter with its source,
+    // `below` controlling whether the source is drawn above it.
+    fn composite_source(
+        &mut self,
+        source: &str,
+        result: String,
+        below: bool,
+        knockout: bool,
+        composite_source: bool,
+    ) -> String {
+        if knockout || !composite_source {
+            result
+        } else if below {
+            self.merge(&[&result, source])
+        } else {
+            self.merge(&[source, &result])
+        }
+    }
+
+    // A blurred, offset and colored copy of the source's alpha, either
+    // outside of it (drop shadows and glows) or inside of it (inner ones).
+    #[allow(clippy::too_many_arguments)]
+    fn shadow(
+        &mut self,
+        source: &str,
+        paint: Paint<'_>,
+        std_deviation: String,
+        offset: (f64, f64),
+        strength: swf::fixed::Sfixed8P8,
+        inner: bool,
+        knockout: bool,
+        composite_source: bool,
+    ) -> String {
+        let blurred = self.blur(source, std_deviation);
+        let shifted = self.offset(&blurred, offset);
+        let shadow = if inner {
+            // Inner shadows are wherever the shifted alpha doesn't reach.
+            let inverted = self.composite(source, &shifted, "out");
+            let strong = self.strengthen(&inverted, strength);
+            let painted = self.paint(&strong, paint);
+            self.composite(&painted, source, "in")
+        } else {
+            let strong = self.strengthen(&shifted, strength);
+            let painted = self.paint(&strong, paint);
+            if knockout {
+                self.composite(&painted, source, "out")
+            } else {
+                painted
+            }
+        };
+        self.composite_source(source, shadow, !inner, knockout, composite_source)
+    }
+
+    // The difference between two copies of the source's blurred alpha,
+    // shifted in opposite directions, gives the highlight and shadow sides.
+    #[allow(clippy::too_many_arguments)]
+    fn bevel(
+        &mut self,
+        source: &str,
+        paint: (Paint<'_>, Paint<'_>),
+        std_deviation: String,
+        (dx, dy): (f64, f64),
+        strength: swf::fixed::Sfixed8P8,
+        (inner, on_top): (bool, bool),
+        knockout: bool,
+        composite_source: bool,
+    ) -> String {
+        let blurred = self.blur(source, std_deviation);
+        let shadow_side = self.offset(&blurred, (dx, dy));
+        let highlight_side = self.offset(&blurred, (-dx, -dy));
+        let bevel = match paint {
+            (Paint::Gradient(stops), _) | (_, Paint::Gradient(stops)) => {
+                // The highlight maps to the start of the gradient, the shadow
+                // to its end, and the middle is where the two cancel out.
+                let k = f64::from(f32::from(strength)) / 2.0;
+                let alpha = self.arithmetic(&shadow_side, &highlight_side, (k, -k, 0.5));
+                self.paint(&alpha, Paint::Gradient(stops))
+            }
+            (highlight, shadow) => {
+                let alpha = self.arithmetic(&highlight_side, &shadow_side, (1.0, -1.0, 0.0));
+                let alpha = self.strengthen(&alpha, strength);
+                let highlight = self.paint(&alpha, highlight);
+                let alpha = self.arithmetic(&shadow_side, &highlight_side, (1.0, -1.0, 0.0));
+                let alpha = self.strengthen(&alpha, strength);
+                let shadow = self.paint(&alpha, shadow);
+                self.merge(&[&shadow, &highlight])
+            }
+        };
+        let bevel = if on_top {
+            bevel
+        } else if inner {
+            self.composite(&bevel, source, "in")
+        } else {
+            self.composite(&bevel, source, "out")
+        };
+        self.composite_source(source, bevel, !inner && !on_top, knockout, composite_source)
+    }
+}
+
+// Returns `None` if none of the filters could be used.
+pub fn to_svg(id: String, filters: &[swf::Filter]) -> Option<Filter> {
+    let mut chain = Chain {
+        filter: Filter::new()
+            .set("id", id)
+            .set("x", "-50%")
+            .set("y", "-50%")
+            .set("width", "200%")
+            .set("height", "200%")
+            .set("color-interpolation-filters", "sRGB"),
+        results: 0,
+    };
+    let mut last = "SourceGraphic".to_string();
+    for filter in filters {
+        last = match filter {
+            swf::Filter::Blur(blur) => {
+                chain.blur(&last, std_deviation(blur.blur_x, blur.blur_y, blur.passes))
+            }
+            swf::Filter::ColorMatrix(color_matrix) => {
+                // Flash's offsets are in `0..=255`, SVG's in `0..=1`.
+                let values: Vec<_> = color_matrix
+                    .matrix
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &x)| {
+                        if i % 5 == 4 {
+                            (f64::from(x) / 255.0).to_string()
+                        } else {
+                            x.to_string()
+                        }
+                    })
+                    .collect();
+                chain.add(primitive(
+                    "feColorMatrix",
+                    vec![
+                        ("in", last.into()),
+                        ("type", "matrix".into()),
+                        ("values", values.join(" ").into()),
+                    ],
+                ))
+            }
+            swf::Filter::Convolution(convolution) => {
+                let (width, height) = (convolution.matrix_width, convolution.matrix_height);
+                if width * height == 0 || convolution.matrix.len() != width * height {
+                    eprintln!("export::svg::filter: invalid {:?}", filter);
+                    continue;
+                }
+                // SVG flips the kernel (unlike Flash), so undo that.
+                let kernel: Vec<_> = convolution
+                    .matrix
+                    .iter()
+                    .rev()
+                    .map(|x| x.to_string())
+                    .collect();
+                let divisor = if convolution.divisor == 0.0 {
+                    1.0
+                } else {
+                    f64::from(convolution.divisor)
+                };
+                // TODO use `default_color` outside the edges.
+                let edge_mode = if convolution.clamp {
+                    "duplicate"
+                } else {
+                    "none"
+                };
+                chain.add(primitive(
+                    "feConvolveMatrix",
+                    vec![
+                        ("in", last.into()),
+                        ("order", format!("{} {}", width, height).into()),
+                        ("kernelMatrix", kernel.join(" ").into()),
+                        ("divisor", divisor.into()),
+                        ("bias", (f64::from(convolution.bias) / 255.0).into()),
+                        ("edgeMode", edge_mode.into()),
+                        (
+                            "preserveAlpha",
+                            convolution.preserve_alpha.to_string().into(),
+                        ),
+                    ],
+                ))
+            }
+            swf::Filter::DropShadow(shadow) => chain.shadow(
+                &last,
+                Paint::Color(shadow.color),
+                std_deviation(shadow.blur_x, shadow.blur_y, shadow.passes),
+                offset(shadow.angle, shadow.distance),
+                shadow.strength,
+                shadow.inner,
+                shadow.knockout,
+                shadow.composite_source,
+            ),
+            swf::Filter::Glow(glow) => chain.shadow(
+                &last,
+                Paint::Color(glow.color),
+                std_deviation(glow.blur_x, glow.blur_y, glow.passes),
+                (0.0, 0.0),
+                glow.strength,
+                glow.inner,
+                glow.knockout,
+                glow.composite_source,
+            ),
+            swf::Filter::GradientGlow(glow) => chain.shadow(
+                &last,
+                Paint::Gradient(&glow.gradient),
+                std_deviation(glow.blur_x, glow.blur_y, glow.passes),
+                offset(glow.angle, glow.distance),
+                glow.strength,
+                glow.inner,
+                glow.knockout,
+                glow.composite_source,
+            ),
+            swf::Filter::Bevel(bevel) => chain.bevel(
+                &last,
+                (
+                    Paint::Color(bevel.highlight_color),
+                    Paint::Color(bevel.shadow_color),
+                ),
+                std_deviation(bevel.blur_x, bevel.blur_y, bevel.passes),
+                offset(bevel.angle, bevel.distance),
+                bevel.strength,
+                (bevel.inner, bevel.on_top),
+                bevel.knockout,
+                bevel.composite_source,
+            ),
+            swf::Filter::GradientBevel(bevel) => chain.bevel(
+                &last,
+                (
+                    Paint::Gradient(&bevel.gradient),
+                    Paint::Gradient(&bevel.gradient),
+                ),
+                std_deviation(bevel.blur_x, bevel.blur_y, bevel.passes),
+                offset(bevel.angle, bevel.distance),
+                bevel.strength,
+                (bevel.inner, bevel.on_top),
+                bevel.knockout,
+                bevel.composite_source,
+            ),
+        };
+    }
+    if chain.results == 0 {
+        return None;
+    }
+    Some(chain.filter)
+}
@@ -1,31 +1,66 @@
+use crate::assets;
+use crate::avm1;
+use crate::avm2;
 use crate::bitmap::Bitmap;
 use crate::button::{self, Button};
 use crate::dictionary::{Character, CharacterId, Dictionary};
 use crate::export::js;
 use crate::shape::{Line, Shape};
 use crate::sound::Sound;
-use crate::timeline::{Frame, Timeline, TimelineBuilder};
+use crate::timeline::{Depth, Frame, Object, Timeline, TimelineBuilder};
 use image::GenericImageView;
 use std::collections::BTreeMap;
+use std::mem;
 use svg::node::element::{
-    path, ClipPath, Definitions, Group, Image, LinearGradient, Path, Pattern, RadialGradient,
-    Rectangle, Script, Stop,
+    path, ClipPath, Definitions, Element, Filter, Group, Image, LinearGradient, Mask, Path,
+    Pattern, RadialGradient, Rectangle, Script, Stop,
 };
+use svg::Node;
 use swf_types as swf;
 
 mod animate;
+mod filter;
+
+// Used to find the movies `ImportAssets` refer to, by their URL.
+pub type LoadImport = dyn Fn(&str) -> Option<swf::Movie>;
 
 #[derive(Default)]
 pub struct Config {
     pub use_js: bool,
+    pub load_import: Option<Box<LoadImport>>,
 }
 
 pub fn export(movie: &swf::Movie, config: Config) -> svg::Document {
+    export_movies(movie, config, false).pop().unwrap().1
+}
+
+// Export each scene (see `Timeline::scenes`) as a movie of its own, along
+// with its name, while only building the definitions they share once.
+pub fn export_scenes(movie: &swf::Movie, config: Config) -> Vec<(String, svg::Document)> {
+    export_movies(movie, config, true)
+}
+
+fn export_movies(
+    movie: &swf::Movie,
+    config: Config,
+    split_scenes: bool,
+) -> Vec<(String, svg::Document)> {
+    let imported_tags = match &config.load_import {
+        Some(load) => assets::resolve_imports(movie, load),
+        None => vec![],
+    };
+
     let mut dictionary = Dictionary::default();
 
+    // FIXME support AVM2, for now only the timeline is exported.
+    let as3 = avm2::is_as3(movie);
+    if as3 {
+        eprintln!("export::svg: AS3 movie, ActionScript will be ignored");
+    }
+
     let mut bg = [0, 0, 0];
     let mut timeline_builder = TimelineBuilder::default();
-    for tag in &movie.tags {
+    for tag in imported_tags.iter().chain(&movie.tags) {
         match tag {
             swf::Tag::SetBackgroundColor(set_bg) => {
                 let c = &set_bg.color;
@@ -42,7 +77,10 @@ pub fn export(movie: &swf::Movie, config: Config) -> svg::Document {
                         swf::Tag::FrameLabel(label) => timeline_builder.frame_label(label),
                         swf::Tag::PlaceObject(place) => timeline_builder.place_object(place),
                         swf::Tag::RemoveObject(remove) => timeline_builder.remove_object(remove),
-                        swf::Tag::DoAction(do_action) => timeline_builder.do_action(do_action),
+                        swf::Tag::DoAction(do_action) if !as3 => {
+                            timeline_builder.do_action(do_action)
+                        }
+                        swf::Tag::DoAction(_) => {}
                         swf::Tag::StartSound(sound) => timeline_builder.start_sound(sound),
                         swf::Tag::SoundStreamHead(head) => timeline_builder.sound_stream_head(head),
                         swf::Tag::SoundStreamBlock(block) => {
@@ -67,10 +105,70 @@ pub fn export(movie: &swf::Movie, config: Config) -> svg::Document {
             swf::Tag::DefineButton(def) => {
                 dictionary.define(CharacterId(def.id), Character::Button(Button::from(def)));
             }
+            swf::Tag::DefineButtonColorTransform(def) => {
+                match dictionary.characters.get_mut(&CharacterId(def.button_id)) {
+                    Some(Character::Button(button)) => button.set_color_transform(&def.transform),
+                    _ => eprintln!(
+                        "export::svg: DefineButtonCxform for missing button {}",
+                        def.button_id
+                    ),
+                }
+            }
+            swf::Tag::DefineButtonSound(def) => {
+                match dictionary.characters.get_mut(&CharacterId(def.button_id)) {
+                    Some(Character::Button(button)) => button.add_sounds(def),
+                    _ => eprintln!(
+                        "export::svg: DefineButtonSound for missing button {}",
+                        def.button_id
+                    ),
+                }
+            }
+            swf::Tag::DoInitAction(init) if !as3 => {
+                timeline_builder.do_init_action(init);
+                dictionary.init_actions.insert(
+                    CharacterId(init.sprite_id),
+                    avm1::Code::parse_and_compile(&init.actions),
+                );
+            }
+            swf::Tag::ExportAssets(export) => {
+                for asset in &export.assets {
+                    dictionary
+                        .export_names
+                        .insert(&asset.name, CharacterId(asset.id));
+                }
+            }
+            // The definitions themselves are in `imported_tags`.
+            swf::Tag::ImportAssets(import) => {
+                for asset in &import.assets {
+                    dictionary
+                        .export_names
+                        .insert(&asset.name, CharacterId(asset.id));
+                }
+            }
+            swf::Tag::FileAttributes(_) => {}
+            swf::Tag::DoAbc(do_abc) => {
+                let name = do_abc.header.as_ref().map_or("", |header| &header.name[..]);
+                match avm2::Abc::parse(&do_abc.data) {
+                    Ok(abc) => dictionary.abc.push((name, abc)),
+                    Err(e) => eprintln!("export::svg: failed to parse ABC {:?}: {}", name, e),
+                }
+            }
+            swf::Tag::SymbolClass(symbol_class) => {
+                for symbol in &symbol_class.symbols {
+                    dictionary
+                        .symbol_classes
+                        .insert(CharacterId(symbol.id), &symbol.name);
+                }
+            }
             swf::Tag::FrameLabel(label) => timeline_builder.frame_label(label),
+            swf::Tag::DefineSceneAndFrameLabelData(data) => {
+                timeline_builder.scene_and_frame_label_data(data)
+            }
             swf::Tag::PlaceObject(place) => timeline_builder.place_object(place),
             swf::Tag::RemoveObject(remove) => timeline_builder.remove_object(remove),
-            swf::Tag::DoAction(do_action) => timeline_builder.do_action(do_action),
+            swf::Tag::DoAction(do_action) if !as3 => timeline_builder.do_action(do_action),
+            // AVM1 actions are ignored by Flash Player in AS3 movies.
+            swf::Tag::DoAction(_) | swf::Tag::DoInitAction(_) => {}
             swf::Tag::StartSound(sound) => timeline_builder.start_sound(sound),
             swf::Tag::SoundStreamHead(head) => timeline_builder.sound_stream_head(head),
             swf::Tag::SoundStreamBlock(block) => timeline_builder.sound_stream_block(block),
@@ -78,7 +176,20 @@ pub fn export(movie: &swf::Movie, config: Config) -> svg::Document {
             _ => eprintln!("unknown tag: {:?}", tag),
         }
     }
+    if as3 {
+        report_as3(&dictionary);
+    }
+
     let timeline = timeline_builder.finish(Frame(movie.header.frame_count));
+    let timelines = if split_scenes {
+        timeline
+            .scenes
+            .iter()
+            .map(|(&start, &name)| (name.to_string(), timeline.clone().into_scene(start)))
+            .collect()
+    } else {
+        vec![(String::new(), timeline)]
+    };
 
     let view_box = {
         let r = &movie.header.frame_size;
@@ -92,23 +203,20 @@ pub fn export(movie: &swf::Movie, config: Config) -> svg::Document {
         svg_defs: Definitions::new(),
         js_defs: js::code! {},
         next_gradient_id: 0,
+        filter_lists: vec![],
     };
 
     for (&id, character) in &dictionary.characters {
         cx.export_character(id, character);
     }
 
-    let mut svg_document = svg::Document::new()
-        .set("xmlns:xlink", "http://www.w3.org/1999/xlink")
-        .set("viewBox", view_box)
-        .set("style", "background: black")
-        .add(
-            Rectangle::new()
-                .set("id", "bg")
-                .set("width", "100%")
-                .set("height", "100%")
-                .set("fill", format!("#{:02x}{:02x}{:02x}", bg[0], bg[1], bg[2])),
-        );
+    if cx.config.use_js {
+        for (id, code) in &dictionary.init_actions {
+            cx.js_defs += js::code! {
+                "init_actions[", id.0, "] = ", js::avm1::export(Some(code)), ";\n"
+            };
+        }
+    }
 
     cx.add_svg_def(
         ClipPath::new().set("id", "viewBox_clip").add(
@@ -120,34 +228,56 @@ pub fn export(movie: &swf::Movie, config: Config) -> svg::Document {
         ),
     );
 
-    if !cx.config.use_js {
-        let svg_body = cx
-            .export_timeline(None, &timeline)
-            .set("clip-path", "url(#viewBox_clip)");
-        svg_document = svg_document.add(cx.svg_defs).add(svg_body);
-    } else {
-        svg_document = svg_document
-            .add(cx.svg_defs)
-            .add(
-                Group::new()
-                    .set("id", "body")
-                    .set("clip-path", "url(#viewBox_clip)"),
-            )
-            .add(
-                js::code! {
-                    "var timeline = ", js::timeline::export(&timeline), ";\n",
-                    "var sounds = [];\n",
-                    "var sprites = [];\n",
-                    "var buttons = [];\n",
-                    cx.js_defs,
-                    "var frame_rate = ", cx.frame_rate, ";\n\n",
-                    include_str!("runtime.js")
-                }
-                .to_svg(),
-            );
+    // NB: each movie starts from the same definitions, so that the
+    // ones added while exporting one timeline aren't in the others.
+    let (svg_defs, filter_lists) = (cx.svg_defs.clone(), cx.filter_lists.clone());
+    timelines
+        .into_iter()
+        .map(|(name, timeline)| {
+            cx.svg_defs = svg_defs.clone();
+            cx.filter_lists = filter_lists.clone();
+            let svg_document = cx.export_movie(&timeline, view_box, bg, &dictionary.export_names);
+            (name, svg_document)
+        })
+        .collect()
+}
+
+// AS3 isn't supported yet, so describe what is being left out.
+fn report_as3(dictionary: &Dictionary<'_>) {
+    for (name, abc) in &dictionary.abc {
+        eprintln!(
+            "export::svg: ABC {:?}: {} classes ({}), {} methods, {} strings",
+            name,
+            abc.instances.len(),
+            abc.class_names().collect::<Vec<_>>().join(", "),
+            abc.methods.len(),
+            abc.constant_pool.strings.len().saturating_sub(1),
+        );
+    }
+    for (id, class) in &dictionary.symbol_classes {
+        if id.0 == 0 {
+            eprintln!("export::svg: document class: {}", class);
+        } else {
+            eprintln!("export::svg: character {}: class {}", id.0, class);
+        }
     }
+}
 
-    svg_document
+// The names of `buttons[x].mouse` handlers (and `buttons[x].sounds`).
+fn mouse_event_name(event: button::Event) -> &'static str {
+    match event {
+        button::Event::KeyPress(_) => unreachable!(),
+
+        button::Event::HoverIn => "hoverIn",
+        button::Event::HoverOut => "hoverOut",
+        button::Event::Down => "down",
+        button::Event::Up => "up",
+        button::Event::DragOut => "dragOut",
+        button::Event::DragIn => "dragIn",
+        button::Event::UpOut => "upOut",
+        button::Event::DownIn => "downIn",
+        button::Event::DownOut => "downOut",
+    }
 }
 
 impl js::Code {
@@ -170,13 +300,112 @@ struct Context {
     svg_defs: Definitions,
     js_defs: js::Code,
     next_gradient_id: usize,
+
+    // Each distinct list of filters gets its own `<filter>`, if possible.
+    filter_lists: Vec<(Vec<swf::Filter>, Option<String>)>,
 }
 
 impl Context {
+    // Export `timeline` as the main timeline of a movie, which uses all
+    // the definitions exported so far.
+    fn export_movie(
+        &mut self,
+        timeline: &Timeline<'_>,
+        view_box: (i32, i32, i32, i32),
+        bg: [u8; 3],
+        export_names: &BTreeMap<&str, CharacterId>,
+    ) -> svg::Document {
+        let mut svg_document = svg::Document::new()
+            .set("xmlns:xlink", "http://www.w3.org/1999/xlink")
+            .set("viewBox", view_box)
+            .set("style", "background: black")
+            .add(
+                Rectangle::new()
+                    .set("id", "bg")
+                    .set("width", "100%")
+                    .set("height", "100%")
+                    .set("fill", format!("#{:02x}{:02x}{:02x}", bg[0], bg[1], bg[2])),
+            );
+
+        if !self.config.use_js {
+            let svg_body = self
+                .export_timeline(None, timeline)
+                .set("clip-path", "url(#viewBox_clip)");
+            svg_document = svg_document.add(self.svg_defs.clone()).add(svg_body);
+        } else {
+            let timeline = js::timeline::export(timeline, &mut |f| self.filter_id(f));
+            svg_document = svg_document
+                .add(self.svg_defs.clone())
+                .add(
+                    Group::new()
+                        .set("id", "body")
+                        .set("clip-path", "url(#viewBox_clip)"),
+                )
+                // NB: the movie definitions are kept in their own script, so
+                // that the runtime can extract them when loading this movie
+                // into another one (e.g. via `loadMovie`).
+                .add(
+                    js::code! {
+                        "var movie = (function() {",
+                        js::code! {
+                            "\nvar timeline = ", timeline, ";\n",
+                            "var sounds = [];\n",
+                            "var sprites = [];\n",
+                            "var buttons = [];\n",
+                            "var init_actions = [];\n",
+                            "var export_names = ", js::object(
+                                export_names
+                                    .iter()
+                                    .map(|(name, id)| (js::string(name), js::code! { id.0 })),
+                            ), ";\n",
+                            self.js_defs.clone(),
+                            "var frame_rate = ", self.frame_rate, ";\n",
+                            "return ", js::object(
+                                [
+                                    "timeline",
+                                    "sounds",
+                                    "sprites",
+                                    "buttons",
+                                    "init_actions",
+                                    "export_names",
+                                    "frame_rate",
+                                ]
+                                .iter()
+                                .map(|&name| (name, js::code! { name })),
+                            ), ";"
+                        }
+                        .indent(),
+                        "\n})();"
+                    }
+                    .to_svg(),
+                )
+                .add(js::code! { include_str!("runtime.js") }.to_svg());
+        }
+
+        svg_document
+    }
+
     fn add_svg_def(&mut self, node: impl svg::Node) {
         self.svg_defs = std::mem::replace(&mut self.svg_defs, Definitions::new()).add(node);
     }
 
+    fn filter_id(&mut self, filters: &[swf::Filter]) -> Option<String> {
+        if filters.is_empty() {
+            return None;
+        }
+        if let Some((_, id)) = self.filter_lists.iter().find(|(f, _)| f == filters) {
+            return id.clone();
+        }
+        let id = format!("filters_{}", self.filter_lists.len());
+        let svg_filter = filter::to_svg(id.clone(), filters);
+        let id = svg_filter.map(|svg_filter| {
+            self.add_svg_def(svg_filter);
+            id
+        });
+        self.filter_lists.push((filters.to_vec(), id.clone()));
+        id
+    }
+
     fn rgba_to_svg(&self, c: &swf::StraightSRgba8) -> String {
         if c.a == 0xff {
             format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
@@ -346,8 +575,9 @@ impl Context {
             // wrt synchronizing the animiation timelines of sprites.
             Character::Sprite(timeline) => {
                 if self.config.use_js {
+                    let timeline = js::timeline::export(timeline, &mut |f| self.filter_id(f));
                     self.js_defs += js::code! {
-                        "sprites[", id.0, "] = ", js::timeline::export(timeline), ";\n"
+                        "sprites[", id.0, "] = ", timeline, ";\n"
                     };
                     return;
                 }
@@ -367,7 +597,7 @@ impl Context {
                     for (&depth, obj) in objects {
                         let id_prefix = format!("{}_d_{}_", svg_id, depth.0);
                         let mut animation = animate::ObjectAnimation::new(id_prefix, Frame(1), 1.0);
-                        animation.add(Frame(0), Some(obj));
+                        animation.add(Frame(0), Some(obj), self.filter_id(&obj.filters));
                         g = g.add(animation.to_svg());
                     }
                     self.add_svg_def(g.set("id", svg_id));
@@ -379,6 +609,13 @@ impl Context {
                         js_button, " = ", js::object(vec![
                             ("mouse", js::code! { "{}" }),
                             ("keyPress", js::array(vec![])),
+                            ("track_as_menu", js::code! { button.track_as_menu }),
+                            ("sounds", js::object(button.sounds.iter().map(|(&event, sound)| {
+                                (
+                                    mouse_event_name(event),
+                                    js::sound::export_sound_info(sound.sound_id, &sound.sound_info),
+                                )
+                            }))),
                         ]), ";\n"
                     };
 
@@ -404,23 +641,11 @@ impl Context {
                         // Generate `buttons[x].mouse.foo = buttons[x].mouse.bar = ...;`.
                         for event in events {
                             self.js_defs += js::code! { js_button, "." };
-                            let mouse_event = match event {
-                                button::Event::KeyPress(c) => {
-                                    self.js_defs += js::code! { "keyPress[", c, "] = " };
-                                    continue;
-                                }
-
-                                button::Event::HoverIn => "hoverIn",
-                                button::Event::HoverOut => "hoverOut",
-                                button::Event::Down => "down",
-                                button::Event::Up => "up",
-                                button::Event::DragOut => "dragOut",
-                                button::Event::DragIn => "dragIn",
-                                button::Event::UpOut => "upOut",
-                                button::Event::DownIn => "downIn",
-                                button::Event::DownOut => "downOut",
-                            };
-                            self.js_defs += js::code! { "mouse.", mouse_event, " = " };
+                            if let button::Event::KeyPress(c) = event {
+                                self.js_defs += js::code! { "keyPress[", c, "] = " };
+                                continue;
+                            }
+                            self.js_defs += js::code! { "mouse.", mouse_event_name(event), " = " };
                         }
 
                         self.js_defs +=
@@ -459,7 +684,7 @@ impl Context {
         self.add_svg_def(g.set("id", svg_id));
     }
 
-    fn export_timeline(&self, id: Option<CharacterId>, timeline: &Timeline) -> Group {
+    fn export_timeline(&mut self, id: Option<CharacterId>, timeline: &Timeline) -> Group {
         let frame_duration = 1.0 / self.frame_rate;
         let movie_duration = timeline.frame_count.0 as f64 * frame_duration;
 
@@ -468,14 +693,163 @@ impl Context {
             return g;
         }
         let id_prefix = id.map_or(String::new(), |id| format!("c_{}_", id.0));
+
+        // Groups of layers being masked (see `clip_depth`), with the
+        // (inclusive) depth they end at, and their mask, innermost last.
+        let mut masked: Vec<(Depth, String, Group)> = vec![];
+        fn current<'g>(
+            g: &'g mut Group,
+            masked: &'g mut [(Depth, String, Group)],
+        ) -> &'g mut Group {
+            match masked.last_mut() {
+                Some((_, _, parent)) => parent,
+                None => g,
+            }
+        }
         for (&depth, layer) in &timeline.layers {
+            while matches!(masked.last(), Some(&(clip_depth, ..)) if depth > clip_depth) {
+                let (_, mask_url, group) = masked.pop().unwrap();
+                current(&mut g, &mut masked).append(group.set("mask", mask_url));
+            }
+
             let id_prefix = format!("{}d_{}_", id_prefix, depth.0);
-            let mut animation =
-                animate::ObjectAnimation::new(id_prefix, timeline.frame_count, movie_duration);
+
+            // Split the layer into spans of frames in which it's either a mask
+            // for the same depths (see `clip_depth`), or not a mask at all.
+            struct Span<'a, 'b> {
+                index: usize,
+                start: Frame,
+                end: Frame,
+                clip_depth: Option<Depth>,
+                frames: Vec<(Frame, Option<&'b Object<'a>>)>,
+            }
+            let mut spans: Vec<Span> = vec![];
             for (&frame, obj) in &layer.frames {
-                animation.add(frame, obj.as_ref());
+                let clip_depth = match obj {
+                    Some(obj) => obj.clip_depth,
+                    None => spans.last().and_then(|span| span.clip_depth),
+                };
+                if let Some(span) = spans.last_mut() {
+                    if span.clip_depth == clip_depth {
+                        span.frames.push((frame, obj.as_ref()));
+                        continue;
+                    }
+                    span.end = frame;
+                }
+                spans.push(Span {
+                    index: spans.len(),
+                    start: frame,
+                    end: timeline.frame_count,
+                    clip_depth,
+                    frames: vec![(frame, obj.as_ref())],
+                });
             }
-            g = g.add(animation.to_svg());
+
+            // Masks go last, with the ones covering the most depths first,
+            // so that the ones covering fewer depths are nested inside them.
+            spans.sort_by_key(|span| {
+                (
+                    span.clip_depth.is_some(),
+                    std::cmp::Reverse(span.clip_depth),
+                    span.index,
+                )
+            });
+
+            for span in spans {
+                let Span {
+                    index,
+                    start,
+                    end,
+                    clip_depth,
+                    frames,
+                } = span;
+                let id_prefix = match index {
+                    0 => id_prefix.clone(),
+                    _ => format!("{}s_{}_", id_prefix, index),
+                };
+                let mut animation = animate::ObjectAnimation::new(
+                    id_prefix.clone(),
+                    timeline.frame_count,
+                    movie_duration,
+                );
+                for &(frame, obj) in &frames {
+                    let filter_id = obj.and_then(|obj| self.filter_id(&obj.filters));
+                    animation.add(frame, obj, filter_id);
+                }
+                if end < timeline.frame_count {
+                    animation.add(end, None, None);
+                }
+                let mask_id = format!("{}mask", id_prefix);
+                let mask_url = format!("url(#{})", mask_id);
+
+                let everything = || {
+                    Rectangle::new()
+                        .set("x", -1_000_000)
+                        .set("y", -1_000_000)
+                        .set("width", 2_000_000)
+                        .set("height", 2_000_000)
+                        .set("fill", "white")
+                };
+                // Outside of the span, masks have to let everything through.
+                let outside_span = |mask: Mask| {
+                    if (start, end) == (Frame(0), timeline.frame_count) {
+                        mask
+                    } else {
+                        mask.add(animate::hide_between(
+                            everything(),
+                            timeline.frame_count,
+                            movie_duration,
+                            start,
+                            end,
+                        ))
+                    }
+                };
+
+                let alpha_blend_mode = frames
+                    .iter()
+                    .filter_map(|&(_, obj)| obj)
+                    .map(|obj| obj.blend_mode)
+                    .find(|&mode| mode == swf::BlendMode::Alpha || mode == swf::BlendMode::Erase);
+                if let Some(clip_depth) = clip_depth {
+                    let mask = Mask::new()
+                        .set("id", mask_id)
+                        .set("style", "mask-type: alpha")
+                        .add(animation.to_svg());
+                    current(&mut g, &mut masked).append(outside_span(mask));
+                    masked.push((clip_depth, mask_url, Group::new()));
+                } else if let Some(blend_mode) = alpha_blend_mode {
+                    // `Alpha` and `Erase` objects aren't shown, but rather keep
+                    // or remove (respectively) what's below them, using their alpha.
+                    let mut mask = Mask::new().set("id", mask_id);
+                    if blend_mode == swf::BlendMode::Alpha {
+                        mask = outside_span(
+                            mask.set("style", "mask-type: alpha")
+                                .add(animation.to_svg()),
+                        );
+                    } else {
+                        // Turn the object black, so it removes from the mask.
+                        let filter_id = format!("{}erase", id_prefix);
+                        let mut black = Element::new("feColorMatrix");
+                        black.assign("values", "0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 1 0");
+                        mask = mask
+                            .add(Filter::new().set("id", filter_id.clone()).add(black))
+                            .add(everything())
+                            .add(
+                                Group::new()
+                                    .set("filter", format!("url(#{})", filter_id))
+                                    .add(animation.to_svg()),
+                            );
+                    }
+                    let container = current(&mut g, &mut masked);
+                    let below = mem::replace(container, Group::new());
+                    *container = Group::new().add(mask).add(below.set("mask", mask_url));
+                } else {
+                    current(&mut g, &mut masked).append(animation.to_svg());
+                }
+            }
+        }
+        while let Some((_, mask_url, group)) = masked.pop() {
+            current(&mut g, &mut masked).append(group.set("mask", mask_url));
         }
         g
     }
@@ -8,7 +8,670 @@
     }
 
     var rt = {};
+    // The main movie's definitions, but more movies can be loaded later.
+    var main_movie = movie;
+    main_movie.id_prefix = '';
+    rt.movies = [main_movie];
+    // NB: loaded movies play at the main movie's frame rate.
+    var frame_rate = main_movie.frame_rate;
+    rt.start_time = Date.now();
+    // Keyboard state, for `Key`, indexed by (DOM) key code.
+    rt.keys_down = Object.create(null);
+    rt.last_key = { code: 0, ascii: 0 };
+    // Objects registered via `Key.addListener` and `Mouse.addListener`.
+    rt.key_listeners = [];
+    rt.mouse_listeners = [];
+    // Call `obj[name](...args)` on every listener that has such a method.
+    rt.broadcast = function(listeners, name, args) {
+        listeners.slice().forEach(function(obj) {
+            if(typeof obj[name] === 'function')
+                obj[name].apply(obj, args || []);
+        });
+    };
+    function addListener(listeners) {
+        return function(obj) {
+            rt.removeListener(listeners, obj);
+            listeners.push(obj);
+        };
+    }
+    rt.removeListener = function(listeners, obj) {
+        var i = listeners.indexOf(obj);
+        if(i == -1)
+            return false;
+        listeners.splice(i, 1);
+        return true;
+    };
+
+    // `Array#sort` flags, shared with `sortOn`.
+    var ARRAY_CASEINSENSITIVE = 1;
+    var ARRAY_DESCENDING = 2;
+    var ARRAY_UNIQUESORT = 4;
+    var ARRAY_RETURNINDEXEDARRAY = 8;
+    var ARRAY_NUMERIC = 16;
+    // Arrays get a prototype with Flash's variants of some methods.
+    var array_proto = Object.create(Array.prototype);
+    function mkArray(values) {
+        return Object.setPrototypeOf(values, array_proto);
+    }
+    function array_compare(flags, key) {
+        return function(a, b) {
+            if(key !== undefined) {
+                a = a[key];
+                b = b[key];
+            }
+            var r;
+            if(flags & ARRAY_NUMERIC) {
+                r = a - b;
+            } else {
+                a = '' + a;
+                b = '' + b;
+                if(flags & ARRAY_CASEINSENSITIVE) {
+                    a = a.toLowerCase();
+                    b = b.toLowerCase();
+                }
+                r = a < b ? -1 : a > b ? 1 : 0;
+            }
+            return flags & ARRAY_DESCENDING ? -r : r;
+        };
+    }
+    // Returns 0 (leaving the array untouched) if `UNIQUESORT` finds equal
+    // elements, or an array of indices if `RETURNINDEXEDARRAY` is used.
+    function array_sort(array, compare, flags) {
+        var indices = array.map(function(_, i) {
+            return i;
+        });
+        indices.sort(function(i, j) {
+            return compare(array[i], array[j]) || i - j;
+        });
+        if(flags & ARRAY_UNIQUESORT)
+            for(var i = 1; i < indices.length; i++)
+                if(compare(array[indices[i - 1]], array[indices[i]]) == 0)
+                    return 0;
+        if(flags & ARRAY_RETURNINDEXEDARRAY)
+            return mkArray(indices);
+        var sorted = indices.map(function(i) {
+            return array[i];
+        });
+        sorted.forEach(function(x, i) {
+            array[i] = x;
+        });
+        return array;
+    }
+    Object.defineProperties(array_proto, {
+        sort: {
+            value: function(compare, flags) {
+                if(typeof compare !== 'function') {
+                    flags = compare;
+                    compare = null;
+                }
+                flags = int(flags);
+                if(compare) {
+                    var f = compare;
+                    compare = function(a, b) {
+                        var r = f(a, b);
+                        return flags & ARRAY_DESCENDING ? -r : r;
+                    };
+                }
+                return array_sort(this, compare || array_compare(flags), flags);
+            },
+        },
+        sortOn: {
+            value: function(key, flags) {
+                flags = int(flags);
+                return array_sort(this, array_compare(flags, key), flags);
+            },
+        },
+        // `concat`, `slice` and `splice` return new arrays.
+        concat: {
+            value: function() {
+                return mkArray(Array.prototype.concat.apply(this.slice(), arguments));
+            },
+        },
+        slice: {
+            value: function() {
+                return mkArray(Array.prototype.slice.apply(this, arguments));
+            },
+        },
+        splice: {
+            value: function() {
+                return mkArray(Array.prototype.splice.apply(this, arguments));
+            },
+        },
+        toString: {
+            value: function() {
+                return this.join(',');
+            },
+        },
+    });
+    function AsArray() {
+        return mkArray(Array.apply(null, arguments));
+    }
+    AsArray.prototype = array_proto;
+    AsArray.CASEINSENSITIVE = ARRAY_CASEINSENSITIVE;
+    AsArray.DESCENDING = ARRAY_DESCENDING;
+    AsArray.UNIQUESORT = ARRAY_UNIQUESORT;
+    AsArray.RETURNINDEXEDARRAY = ARRAY_RETURNINDEXEDARRAY;
+    AsArray.NUMERIC = ARRAY_NUMERIC;
+
+    // Flash's `String` is mostly like JS's, except `String(undefined)`.
+    function AsString(x) {
+        if(this instanceof AsString)
+            return new String(x === undefined ? '' : x);
+        return x === undefined ? '' : '' + x;
+    }
+    AsString.prototype = String.prototype;
+    AsString.fromCharCode = String.fromCharCode;
+
+    // JS constructors, wrapped so that scripts adding or replacing their
+    // static members (e.g. `Date.UTC`) don't affect the page's own.
+    function wrapConstructor(native) {
+        function Wrapped() {
+            if(this instanceof Wrapped) {
+                var args = [null].concat(Array.prototype.slice.call(arguments));
+                return new (Function.prototype.bind.apply(native, args))();
+            }
+            return native.apply(null, arguments);
+        }
+        Wrapped.prototype = native.prototype;
+        return Object.setPrototypeOf(Wrapped, native);
+    }
+
+    var Key = {
+        BACKSPACE: 8,
+        TAB: 9,
+        ENTER: 13,
+        SHIFT: 16,
+        CONTROL: 17,
+        CAPSLOCK: 20,
+        ESCAPE: 27,
+        SPACE: 32,
+        PGUP: 33,
+        PGDN: 34,
+        END: 35,
+        HOME: 36,
+        LEFT: 37,
+        UP: 38,
+        RIGHT: 39,
+        DOWN: 40,
+        INSERT: 45,
+        DELETEKEY: 46,
+        isDown: function(code) {
+            return !!rt.keys_down[int(code)];
+        },
+        isToggled: function(code) {
+            // FIXME only Caps Lock is tracked by browsers.
+            return int(code) == Key.CAPSLOCK && !!rt.caps_lock;
+        },
+        getCode: function() {
+            return rt.last_key.code;
+        },
+        getAscii: function() {
+            return rt.last_key.ascii;
+        },
+        addListener: addListener(rt.key_listeners),
+        removeListener: function(obj) {
+            return rt.removeListener(rt.key_listeners, obj);
+        },
+    };
+
+    var Mouse = {
+        hide: function() {
+            document.documentElement.style.cursor = 'none';
+            return 1;
+        },
+        show: function() {
+            document.documentElement.style.cursor = '';
+            return 0;
+        },
+        addListener: addListener(rt.mouse_listeners),
+        removeListener: function(obj) {
+            return rt.removeListener(rt.mouse_listeners, obj);
+        },
+    };
+
+    // Both `setInterval(f, ms, ...args)` and `setInterval(obj, 'method', ms, ...args)`.
+    function timer(set) {
+        return function(f, ms) {
+            var args = Array.prototype.slice.call(arguments, 2);
+            var obj = null;
+            if(typeof f !== 'function') {
+                obj = f;
+                f = obj && obj[ms];
+                ms = args.shift();
+                if(typeof f !== 'function')
+                    return;
+            }
+            return set(function() {
+                f.apply(obj, args);
+            }, +ms || 0);
+        };
+    }
+
+    // Volume (0-100) and pan (-100-100) settings, per target timeline
+    // (see `Sound`), with `null` used for the whole movie.
+    rt.global_sound_settings = { volume: 100, pan: 0 };
+    rt.soundSettings = function(timeline) {
+        if(!timeline)
+            return rt.global_sound_settings;
+        if(!timeline.sound_settings)
+            timeline.sound_settings = { volume: 100, pan: 0 };
+        return timeline.sound_settings;
+    };
+    // Volumes of the timeline and all of its ancestors combine.
+    rt.soundVolume = function(timeline) {
+        var volume = rt.global_sound_settings.volume / 100;
+        for(var t = timeline; t; t = t.parent)
+            if(t.sound_settings)
+                volume *= t.sound_settings.volume / 100;
+        return Math.min(Math.max(volume, 0), 1);
+    };
+    // Sounds started via `Sound#start`.
+    rt.playing_sounds = [];
+    rt.updateSoundVolumes = function() {
+        forEachSound(function(sound) {
+            if(sound.userTimeline)
+                sound.volume = rt.soundVolume(sound.userTimeline);
+        });
+        rt.playing_sounds.forEach(function(playing) {
+            playing.audio.volume = rt.soundVolume(playing.timeline);
+        });
+    };
+    rt.stopSound = function(playing) {
+        playing.audio.pause();
+        var i = rt.playing_sounds.indexOf(playing);
+        if(i != -1)
+            rt.playing_sounds.splice(i, 1);
+    };
+    // Play an event sound (e.g. from a button), which, unlike timeline
+    // sounds, can overlap with itself, unless `no_restart` is set.
+    rt.playEventSound = function(timeline, sound_data) {
+        var sound = timeline.movie.sounds[sound_data.character];
+        if(!sound)
+            return;
+        if(sound_data.no_restart && rt.playing_sounds.some(function(playing) {
+            return playing.character == sound_data.character;
+        }))
+            return;
+        var audio = sound.cloneNode();
+        var playing = {
+            audio: audio,
+            timeline: timeline,
+            character: sound_data.character,
+        };
+        var loops = Math.max(sound_data.loops || 1, 1);
+        audio.addEventListener('ended', function() {
+            if(--loops > 0) {
+                audio.currentTime = 0;
+                audio.play();
+                return;
+            }
+            rt.stopSound(playing);
+        });
+        audio.volume = rt.soundVolume(timeline);
+        rt.setAudioPan(audio, rt.soundSettings(timeline).pan);
+        rt.playing_sounds.push(playing);
+        var promise = audio.play();
+        if(promise && promise.catch)
+            promise.catch(function(e) {
+                console.error('failed to play sound: ' + e.toString());
+            });
+    };
+    // HACK HTML audio can't be panned, so route it through Web Audio
+    // (only once needed, as that can run into autoplay restrictions).
+    rt.setAudioPan = function(audio, pan) {
+        if(!audio.panner) {
+            if(pan == 0)
+                return;
+            var AudioContext = window.AudioContext || window.webkitAudioContext;
+            if(!AudioContext)
+                return;
+            var context = rt.audio_context = rt.audio_context || new AudioContext();
+            if(!context.createStereoPanner)
+                return;
+            audio.panner = context.createStereoPanner();
+            context.createMediaElementSource(audio).connect(audio.panner);
+            audio.panner.connect(context.destination);
+        }
+        audio.panner.pan.value = pan / 100;
+    };
+
+    // `new Sound(target)` controls the sounds of a movie clip
+    // (or the whole movie, without a target), and can play sounds
+    // exported from the library (via `attachSound`) on its own.
+    function Sound(target) {
+        if(!(this instanceof Sound))
+            return new Sound(target);
+        var timeline = null;
+        if(target !== undefined && target !== null) {
+            timeline = rt.resolveTarget(rt.levels[0], target);
+            if(!timeline)
+                console.error('Sound: unknown target', target);
+        }
+        Object.defineProperty(this, 'timeline', { value: timeline });
+        // Sounds are attached from the library of the target's movie.
+        Object.defineProperty(this, 'movie', { value: (timeline || rt.levels[0]).movie });
+    }
+    Sound.prototype.attachSound = function(name) {
+        var id = this.movie.export_names[name];
+        if(!this.movie.sounds[id])
+            return console.error('Sound#attachSound: unknown sound', name);
+        if(this.playing)
+            rt.stopSound(this.playing);
+        this.playing = null;
+        this.character = id;
+    };
+    // Start playing the attached sound `loops` times, from `offset` seconds.
+    Sound.prototype.start = function(offset, loops) {
+        if(!this.character)
+            return;
+        if(this.playing)
+            rt.stopSound(this.playing);
+        var self = this;
+        var audio = this.movie.sounds[this.character].cloneNode();
+        var playing = this.playing = {
+            audio: audio,
+            timeline: this.timeline,
+            character: this.character,
+        };
+        offset = +offset || 0;
+        loops = Math.max(int(loops), 1);
+        audio.addEventListener('ended', function() {
+            if(self.playing !== playing)
+                return;
+            if(--loops > 0) {
+                audio.currentTime = offset;
+                audio.play();
+                return;
+            }
+            rt.stopSound(playing);
+            self.playing = null;
+            if(typeof self.onSoundComplete === 'function')
+                self.onSoundComplete();
+        });
+        audio.volume = rt.soundVolume(this.timeline);
+        rt.setAudioPan(audio, rt.soundSettings(this.timeline).pan);
+        audio.currentTime = offset;
+        rt.playing_sounds.push(playing);
+        var promise = audio.play();
+        if(promise && promise.catch)
+            promise.catch(function(e) {
+                console.error('failed to play sound: ' + e.toString());
+            });
+    };
+    // Stop all of the sounds played through the target (or just the ones
+    // exported under the given name), including any timeline sounds.
+    Sound.prototype.stop = function(name) {
+        var timeline = this.timeline;
+        var id = name === undefined ? undefined : this.movie.export_names[name];
+        function inTarget(t) {
+            if(!timeline)
+                return true;
+            for(; t; t = t.parent)
+                if(t === timeline)
+                    return true;
+            return false;
+        }
+        rt.playing_sounds.slice().forEach(function(playing) {
+            if(inTarget(playing.timeline) && (id === undefined || playing.character === id))
+                rt.stopSound(playing);
+        });
+        if(this.playing && rt.playing_sounds.indexOf(this.playing) == -1)
+            this.playing = null;
+        if(id === undefined)
+            forEachSound(function(sound) {
+                if(sound.userTimeline && inTarget(sound.userTimeline)) {
+                    sound.userTimeline = null;
+                    sound.pause();
+                }
+            });
+    };
+    Sound.prototype.setVolume = function(volume) {
+        rt.soundSettings(this.timeline).volume = +volume || 0;
+        rt.updateSoundVolumes();
+    };
+    Sound.prototype.getVolume = function() {
+        return rt.soundSettings(this.timeline).volume;
+    };
+    Sound.prototype.setPan = function(pan) {
+        var settings = rt.soundSettings(this.timeline);
+        settings.pan = Math.min(Math.max(+pan || 0, -100), 100);
+        rt.playing_sounds.forEach(function(playing) {
+            if(playing.timeline === this.timeline)
+                rt.setAudioPan(playing.audio, settings.pan);
+        }, this);
+    };
+    Sound.prototype.getPan = function() {
+        return rt.soundSettings(this.timeline).pan;
+    };
+    // Both in milliseconds, like in Flash.
+    Sound.prototype.getDuration = function() {
+        var audio = this.playing ? this.playing.audio : this.movie.sounds[this.character];
+        return audio && audio.duration ? Math.round(audio.duration * 1000) : 0;
+    };
+    Sound.prototype.getPosition = function() {
+        return this.playing ? Math.round(this.playing.audio.currentTime * 1000) : 0;
+    };
+
+    // Hooks the embedder can provide (via `window.flashback`, before
+    // the runtime starts), e.g. to proxy network requests.
+    var host = window.flashback || {};
+
+    // Send `request` (with `url`, `method` and, for `POST`, URL-encoded
+    // `data`), then call `callback(error, text)` once it's done.
+    rt.transport = host.transport || function(request, callback) {
+        var xhr = new XMLHttpRequest();
+        xhr.open(request.method, request.url);
+        if(request.data !== null)
+            xhr.setRequestHeader('Content-Type', 'application/x-www-form-urlencoded');
+        xhr.onload = function() {
+            if(xhr.status >= 200 && xhr.status < 300)
+                callback(null, xhr.responseText);
+            else
+                callback(new Error('HTTP ' + xhr.status));
+        };
+        xhr.onerror = function() {
+            callback(new Error('network error'));
+        };
+        xhr.send(request.data);
+    };
+
+    // Variables are sent as `application/x-www-form-urlencoded`, leaving
+    // out anything that doesn't have an useful string representation.
+    rt.encodeVariables = function(vars) {
+        var pairs = [];
+        for(var name in vars) {
+            var value = vars[name];
+            if(typeof value === 'function' || typeof value === 'object' && value !== null)
+                continue;
+            pairs.push(encodeURIComponent(name) + '=' + encodeURIComponent(value));
+        }
+        return pairs.join('&');
+    };
+    rt.decodeVariables = function(text) {
+        function decode(s) {
+            s = s.replace(/\+/g, ' ');
+            try {
+                return decodeURIComponent(s);
+            } catch(e) {
+                return unescape(s);
+            }
+        }
+        var vars = {};
+        text.replace(/^\s+|\s+$/g, '').split('&').forEach(function(pair) {
+            if(!pair)
+                return;
+            var eq = pair.indexOf('=');
+            if(eq == -1)
+                vars[decode(pair)] = '';
+            else
+                vars[decode(pair.slice(0, eq))] = decode(pair.slice(eq + 1));
+        });
+        return vars;
+    };
+    // Build the request for loading an URL, with `vars` sent via `method`.
+    rt.mkRequest = function(url, method, vars) {
+        url = '' + url;
+        var data = vars ? rt.encodeVariables(vars) : '';
+        if(method == 'POST')
+            return { url: url, method: 'POST', data: data };
+        if(method == 'GET' && data)
+            url += (url.indexOf('?') == -1 ? '?' : '&') + data;
+        return { url: url, method: 'GET', data: null };
+    };
+    rt.loadVariables = function(request, callback) {
+        rt.transport(request, function(error, text) {
+            if(error)
+                return console.error('failed to load variables from', request.url, error);
+            callback(rt.decodeVariables(text));
+        });
+    };
+
+    // `LoadVars` objects hold variables as their own properties.
+    function LoadVars() {
+        if(!(this instanceof LoadVars))
+            return new LoadVars();
+    }
+    function loadVarsInto(target, request) {
+        rt.transport(request, function(error, text) {
+            // NB: `loaded` shouldn't be sent along with the variables.
+            Object.defineProperty(target, 'loaded', { value: !error, writable: true, configurable: true });
+            target.onData(error ? undefined : text);
+        });
+    }
+    Object.defineProperties(LoadVars.prototype, {
+        loaded: { value: false, writable: true },
+        load: {
+            value: function(url) {
+                loadVarsInto(this, rt.mkRequest(url, 'GET', null));
+                return true;
+            },
+        },
+        send: {
+            value: function(url, target, method) {
+                rt.navigate(rt.mkRequest(url, method || 'POST', this), target);
+                return true;
+            },
+        },
+        sendAndLoad: {
+            value: function(url, target, method) {
+                loadVarsInto(target, rt.mkRequest(url, method || 'POST', this));
+                return true;
+            },
+        },
+        onData: {
+            writable: true,
+            value: function(text) {
+                if(text !== undefined)
+                    this.decode(text);
+                if(typeof this.onLoad === 'function')
+                    this.onLoad(text !== undefined);
+            },
+        },
+        decode: {
+            value: function(text) {
+                var vars = rt.decodeVariables(text);
+                for(var name in vars)
+                    this[name] = vars[name];
+            },
+        },
+        toString: {
+            value: function() {
+                return rt.encodeVariables(this);
+            },
+        },
+        getBytesLoaded: {
+            value: function() {
+                return this.loaded ? 1 : 0;
+            },
+        },
+        getBytesTotal: {
+            value: function() {
+                return 1;
+            },
+        },
+    });
+
+    // Open a request in a browser window, with a form for `POST`.
+    rt.navigate = function(request, target) {
+        target = target ? '' + target : '_self';
+        if(request.method != 'POST')
+            return window.open(request.url, target);
+        var html = 'http://www.w3.org/1999/xhtml';
+        var form = document.createElementNS(html, 'form');
+        form.setAttribute('method', 'POST');
+        form.setAttribute('action', request.url);
+        form.setAttribute('target', target);
+        var vars = rt.decodeVariables(request.data);
+        for(var name in vars) {
+            var input = document.createElementNS(html, 'input');
+            input.setAttribute('type', 'hidden');
+            input.setAttribute('name', name);
+            input.setAttribute('value', vars[name]);
+            form.appendChild(input);
+        }
+        document.documentElement.appendChild(form);
+        form.submit();
+        form.remove();
+    };
+
+    // Shared objects are kept in `localStorage`, as JSON, and are
+    // written out on `flush` and whenever the page is closed.
+    rt.shared_objects = Object.create(null);
+    function SharedObject() {}
+    SharedObject.getLocal = function(name, local_path) {
+        var key = 'flashback.SharedObject:' + (local_path || location.pathname) + ':' + name;
+        if(rt.shared_objects[key])
+            return rt.shared_objects[key];
+        var so = Object.create(SharedObject.prototype);
+        Object.defineProperty(so, 'key', { value: key });
+        so.data = {};
+        try {
+            var json = localStorage.getItem(key);
+            if(json !== null)
+                so.data = rt.restoreSharedData(JSON.parse(json));
+        } catch(e) {
+            console.error('SharedObject.getLocal: failed to read', key, e);
+        }
+        return rt.shared_objects[key] = so;
+    };
+    // JSON loses the Flash-specific prototype of arrays.
+    rt.restoreSharedData = function(x) {
+        if(typeof x !== 'object' || x === null)
+            return x;
+        for(var name in x)
+            x[name] = rt.restoreSharedData(x[name]);
+        return Array.isArray(x) ? mkArray(x) : x;
+    };
+    SharedObject.prototype.flush = function() {
+        var status = 'SharedObject.Flush.Success';
+        try {
+            localStorage.setItem(this.key, JSON.stringify(this.data));
+        } catch(e) {
+            console.error('SharedObject#flush: failed to write', this.key, e);
+            status = 'SharedObject.Flush.Failed';
+        }
+        if(typeof this.onStatus === 'function')
+            this.onStatus({ code: status, level: status == 'SharedObject.Flush.Success' ? 'status' : 'error' });
+        return status == 'SharedObject.Flush.Success';
+    };
+    SharedObject.prototype.clear = function() {
+        this.data = {};
+        try {
+            localStorage.removeItem(this.key);
+        } catch(e) {}
+    };
+    SharedObject.prototype.getSize = function() {
+        return JSON.stringify(this.data).length;
+    };
+    window.addEventListener('beforeunload', function() {
+        for(var key in rt.shared_objects)
+            rt.shared_objects[key].flush();
+    });
+
+    rt.global = null;
     rt.mkGlobalScope = function() {
+        if(rt.global)
+            return rt.global;
         var o = Object.create(null);
         function def(name, x) {
             Object.defineProperty(o, name, { value: x });
@@ -16,10 +679,54 @@
         def('hasOwnProperty', function(o, x) {
             return Object.prototype.hasOwnProperty.call(o, x);
         });
-        // HACK(eddyb) trap writes.
-        if(Object.freeze)
-            return Object.freeze(o);
-        return o;
+        def('_global', o);
+
+        // Mostly the same between ActionScript and JS.
+        def('Math', Object.create(Math));
+        def('Date', wrapConstructor(Date));
+        def('Number', wrapConstructor(Number));
+        def('Boolean', wrapConstructor(Boolean));
+        def('Object', wrapConstructor(Object));
+        def('NaN', NaN);
+        def('Infinity', Infinity);
+        def('isNaN', isNaN);
+        def('isFinite', isFinite);
+        def('parseInt', parseInt);
+        def('parseFloat', parseFloat);
+        def('escape', escape);
+        def('unescape', unescape);
+
+        def('Array', AsArray);
+        def('String', AsString);
+        def('Key', Key);
+        def('Mouse', Mouse);
+        def('Sound', Sound);
+        def('LoadVars', LoadVars);
+        def('SharedObject', SharedObject);
+
+        // Also used by the `RandomNumber`, `ToInteger` and `GetTime` actions.
+        def('random', function(n) {
+            return Math.floor(Math.random() * int(n));
+        });
+        def('int', function(x) {
+            return int(x);
+        });
+        def('getTimer', function() {
+            return Date.now() - rt.start_time;
+        });
+
+        def('setInterval', timer(window.setInterval.bind(window)));
+        def('clearInterval', function(id) {
+            window.clearInterval(id);
+        });
+        def('setTimeout', timer(window.setTimeout.bind(window)));
+        def('clearTimeout', function(id) {
+            window.clearTimeout(id);
+        });
+
+        // NB: the built-ins above can't be replaced, but scripts can add
+        // their own globals (e.g. `_global.score = 0`).
+        return rt.global = o;
     };
     rt.mkLocalScope = function(_this) {
         var o = Object.create(_this);
@@ -32,14 +739,682 @@
             return Object.freeze(o);
         return o;
     };
+    // Levels hold the root timeline (`_level0`) and any loaded movies.
+    rt.levels = [];
+    rt.child = function(timeline, name) {
+        var layer = timeline.layers[timeline.named[name]];
+        return layer && layer.sprite;
+    };
+    // Resolve a target path relative to `timeline`, in either the SWF4
+    // slash syntax (`/clip/sub`, `../sub`), or the SWF5 dot syntax
+    // (`_root.clip.sub`, `_parent._parent`, `_level0.clip`).
+    // Movie clips are accepted too, as they convert to their dot path.
+    rt.resolveTarget = function(timeline, target) {
+        if(target === undefined || target === null)
+            return timeline;
+        target = '' + target;
+        var t = timeline;
+        if(target[0] == '/') {
+            t = timeline.root;
+            target = target.slice(1);
+        }
+        var parts = target.split(/[\/.]/);
+        for(var i = 0; i < parts.length && t; i++) {
+            var part = parts[i];
+            var level = /^_level(\d+)$/.exec(part);
+            if(part === '')
+                continue;
+            else if(part == '..' || part == '_parent')
+                t = t.parent;
+            else if(part == '_root')
+                t = t.root;
+            else if(level)
+                t = rt.levels[+level[1]];
+            else
+                t = rt.child(t, part);
+        }
+        return t || null;
+    };
+    // Split a variable path (`/clip:var`, `_root.clip.var`) into the
+    // timeline holding the variable (if any), and the variable name.
+    rt.resolveVariable = function(timeline, path) {
+        path = '' + path;
+        var split = path.lastIndexOf(':');
+        if(split == -1)
+            split = path.lastIndexOf('.');
+        if(split == -1)
+            return { timeline: timeline, name: path };
+        return {
+            timeline: rt.resolveTarget(timeline, path.slice(0, split)),
+            name: path.slice(split + 1),
+        };
+    };
+    // Variable lookups only see what's actually on the timeline, i.e.
+    // movie clip properties (e.g. `_x`), timeline variables and named
+    // children, never the methods of movie clips or anything inherited
+    // (so a variable named e.g. `play` can still come from `_global`).
+    rt.isProperty = function(name) {
+        return Object.prototype.hasOwnProperty.call(rt.properties, name);
+    };
+    rt.hasVariable = function(timeline, name) {
+        return rt.isProperty(name) ||
+            Object.prototype.hasOwnProperty.call(timeline.vars, name) ||
+            !!rt.child(timeline, name);
+    };
+
+    // Frame labels are case-insensitive (and exported lowercase).
+    rt.labelFrame = function(timeline, label) {
+        label = ('' + label).toLowerCase();
+        if(Object.prototype.hasOwnProperty.call(timeline.labels, label))
+            return timeline.labels[label];
+    };
+
+    var identity_matrix = [1, 0, 0, 1, 0, 0];
+    var identity_color_transform = [
+        1, 0, 0, 0, 0,
+        0, 1, 0, 0, 0,
+        0, 0, 1, 0, 0,
+        0, 0, 0, 1, 0,
+    ];
+    function layer_matrix(layer) {
+        return ((layer && layer.matrix) || identity_matrix).slice();
+    }
+    function layer_color_transform(layer) {
+        return ((layer && layer.color_transform) || identity_color_transform).slice();
+    }
+    // Scripted changes stick until the timeline places the object again.
+    function layer_set(layer, field, value) {
+        if(!layer)
+            return;
+        layer[field] = value;
+        layer.applyTransform();
+    }
+    function layer_bounds(layer) {
+        if(!layer)
+            return { width: 0, height: 0 };
+        var m = layer_matrix(layer);
+        var bbox = layer.container.getBBox();
+        return {
+            width: Math.abs(m[0]) * bbox.width + Math.abs(m[2]) * bbox.height,
+            height: Math.abs(m[1]) * bbox.width + Math.abs(m[3]) * bbox.height,
+        };
+    }
+    function axis_scale(m, i) {
+        return Math.sqrt(m[i] * m[i] + m[i + 1] * m[i + 1]);
+    }
+    function set_axis_scale(m, i, scale) {
+        var old = axis_scale(m, i);
+        if(old) {
+            m[i] *= scale / old;
+            m[i + 1] *= scale / old;
+        } else {
+            m[i] = i == 0 ? scale : 0;
+            m[i + 1] = i == 0 ? 0 : scale;
+        }
+    }
+
+    // Movie clip properties, shared by `GetProperty` / `SetProperty`
+    // and `GetMember` / `SetMember` (e.g. `_x` and `clip._x`).
+    rt.properties = {
+        _x: {
+            get: function(t) {
+                return layer_matrix(t.layer)[4] / 20;
+            },
+            set: function(t, x) {
+                var m = layer_matrix(t.layer);
+                m[4] = x * 20;
+                layer_set(t.layer, 'matrix', m);
+            },
+        },
+        _y: {
+            get: function(t) {
+                return layer_matrix(t.layer)[5] / 20;
+            },
+            set: function(t, y) {
+                var m = layer_matrix(t.layer);
+                m[5] = y * 20;
+                layer_set(t.layer, 'matrix', m);
+            },
+        },
+        _xscale: {
+            get: function(t) {
+                return axis_scale(layer_matrix(t.layer), 0) * 100;
+            },
+            set: function(t, scale) {
+                var m = layer_matrix(t.layer);
+                set_axis_scale(m, 0, scale / 100);
+                layer_set(t.layer, 'matrix', m);
+            },
+        },
+        _yscale: {
+            get: function(t) {
+                return axis_scale(layer_matrix(t.layer), 2) * 100;
+            },
+            set: function(t, scale) {
+                var m = layer_matrix(t.layer);
+                set_axis_scale(m, 2, scale / 100);
+                layer_set(t.layer, 'matrix', m);
+            },
+        },
+        _rotation: {
+            get: function(t) {
+                var m = layer_matrix(t.layer);
+                return Math.atan2(m[1], m[0]) * 180 / Math.PI;
+            },
+            set: function(t, degrees) {
+                var m = layer_matrix(t.layer);
+                var delta = degrees * Math.PI / 180 - Math.atan2(m[1], m[0]);
+                var cos = Math.cos(delta), sin = Math.sin(delta);
+                for(var i = 0; i < 4; i += 2) {
+                    var x = m[i], y = m[i + 1];
+                    m[i] = x * cos - y * sin;
+                    m[i + 1] = x * sin + y * cos;
+                }
+                layer_set(t.layer, 'matrix', m);
+            },
+        },
+        _alpha: {
+            get: function(t) {
+                return layer_color_transform(t.layer)[18] * 100;
+            },
+            set: function(t, alpha) {
+                var c = layer_color_transform(t.layer);
+                c[18] = alpha / 100;
+                layer_set(t.layer, 'color_transform', c);
+            },
+        },
+        _visible: {
+            get: function(t) {
+                return !t.layer || t.layer.visible;
+            },
+            set: function(t, visible) {
+                layer_set(t.layer, 'visible', !!visible);
+            },
+        },
+        // Nothing is actually cached, this only keeps track of the flag.
+        cacheAsBitmap: {
+            get: function(t) {
+                return !!(t.layer && t.layer.cache_as_bitmap);
+            },
+            set: function(t, cache_as_bitmap) {
+                layer_set(t.layer, 'cache_as_bitmap', !!cache_as_bitmap);
+            },
+        },
+        _width: {
+            get: function(t) {
+                return layer_bounds(t.layer).width / 20;
+            },
+            set: function(t, width) {
+                var old = layer_bounds(t.layer).width / 20;
+                if(!old)
+                    return;
+                var m = layer_matrix(t.layer);
+                m[0] *= width / old;
+                m[1] *= width / old;
+                layer_set(t.layer, 'matrix', m);
+            },
+        },
+        _height: {
+            get: function(t) {
+                return layer_bounds(t.layer).height / 20;
+            },
+            set: function(t, height) {
+                var old = layer_bounds(t.layer).height / 20;
+                if(!old)
+                    return;
+                var m = layer_matrix(t.layer);
+                m[2] *= height / old;
+                m[3] *= height / old;
+                layer_set(t.layer, 'matrix', m);
+            },
+        },
+        _currentframe: {
+            get: function(t) {
+                return Math.max(t.renderedFrame, 0) + 1;
+            },
+        },
+        _totalframes: {
+            get: function(t) {
+                return t.frame_count;
+            },
+        },
+        // All of frames are loaded ahead of time.
+        _framesloaded: {
+            get: function(t) {
+                return t.frame_count;
+            },
+        },
+        _name: {
+            get: function(t) {
+                return (t.layer && t.layer.name) || '';
+            },
+            set: function(t, name) {
+                var layer = t.layer;
+                if(!layer || !t.parent)
+                    return;
+                if(layer.name)
+                    t.parent.named[layer.name] = null;
+                layer.name = '' + name;
+                t.parent.named[layer.name] = layer.depth;
+            },
+        },
+        _target: {
+            get: function(t) {
+                var path = '';
+                for(; t.parent; t = t.parent)
+                    path = '/' + rt.properties._name.get(t) + path;
+                return path || '/';
+            },
+        },
+        // TODO implement `startDrag` and track the drop target.
+        _droptarget: {
+            get: function() {
+                return '';
+            },
+        },
+    };
+
+    rt.forEachTimeline = function(f) {
+        function visit(t) {
+            f(t);
+            t.layers.forEach(function(layer) {
+                if(layer.sprite)
+                    visit(layer.sprite);
+            });
+        }
+        rt.levels.forEach(visit);
+    };
+    // Rename all IDs in an SVG subtree (and the references to them),
+    // to avoid conflicts between movies (which all use e.g. `c_1`).
+    rt.prefixIds = function(element, prefix) {
+        Array.prototype.slice.call(element.attributes).forEach(function(attr) {
+            var value = attr.value;
+            if(attr.name == 'id')
+                value = prefix + value;
+            else if(attr.localName == 'href' && value[0] == '#')
+                value = '#' + prefix + value.slice(1);
+            else
+                value = value.replace(/url\(#/g, 'url(#' + prefix);
+            if(value != attr.value)
+                element.setAttributeNS(attr.namespaceURI, attr.name, value);
+        });
+        Array.prototype.forEach.call(element.childNodes, function(child) {
+            if(child.nodeType == 1)
+                rt.prefixIds(child, prefix);
+        });
+    };
+    // Extract the definitions out of the SVG a movie was converted to,
+    // i.e. its `<defs>` and the script defining `movie`.
+    rt.next_movie_id = 1;
+    rt.parseMovie = function(svg_text) {
+        var svg_ns = 'http://www.w3.org/2000/svg';
+        var doc = new DOMParser().parseFromString(svg_text, 'image/svg+xml');
+        var script = doc.getElementsByTagNameNS(svg_ns, 'script')[0];
+        if(!script)
+            throw new Error('not a converted movie');
+        var movie = new Function(script.textContent + '\nreturn movie;')();
+        movie.id_prefix = 'm' + rt.next_movie_id++ + '_';
+        var defs = doc.getElementsByTagNameNS(svg_ns, 'defs')[0];
+        if(defs) {
+            rt.prefixIds(defs, movie.id_prefix);
+            movie.defs = document.importNode(defs, true);
+        }
+        return movie;
+    };
+    // Load a movie via the host's `loadMovie`, if provided, which has
+    // to pass the movie, converted to SVG, to its callback. Otherwise,
+    // the converted SVG is assumed to be next to the original SWF.
+    rt.fetchMovie = function(request, callback) {
+        function done(error, svg_text) {
+            var movie;
+            try {
+                if(error)
+                    throw error;
+                movie = rt.parseMovie(svg_text);
+            } catch(e) {
+                return console.error('failed to load movie from', request.url, e);
+            }
+            rt.movies.push(movie);
+            if(movie.defs)
+                rt.stage.ownerSVGElement.appendChild(movie.defs);
+            callback(movie);
+        }
+        if(host.loadMovie)
+            return host.loadMovie(request, done);
+        rt.transport({
+            url: request.url.replace(/\.swf($|[?#])/i, '.svg$1'),
+            method: request.method,
+            data: request.data,
+        }, done);
+    };
+    // Levels other than `_level0` get their own containers, above it.
+    rt.loadLevel = function(level, movie) {
+        // Loading a movie into `_level0` replaces everything.
+        if(level == 0)
+            rt.levels.forEach(function(_, level) {
+                rt.unloadLevel(level);
+            });
+        else
+            rt.unloadLevel(level);
+        var container = rt.stage;
+        if(level != 0) {
+            container = svg_element('g');
+            container.setAttribute('clip-path', rt.stage.getAttribute('clip-path'));
+            var next = null;
+            rt.levels.forEach(function(t, i) {
+                if(!next && i > level)
+                    next = t.container;
+            });
+            rt.stage.parentNode.insertBefore(container, next || rt.stage.nextSibling);
+        }
+        rt.levels[level] = new Timeline(movie, movie.timeline, container, movie.id_prefix);
+    };
+    rt.unloadLevel = function(level) {
+        var t = rt.levels[level];
+        if(!t)
+            return;
+        t.detachLayers();
+        if(t.container != rt.stage)
+            t.container.remove();
+        delete rt.levels[level];
+    };
+    // Replace the contents of a movie clip with a loaded movie (or nothing),
+    // keeping its instance properties (e.g. name, transform, clip events).
+    rt.loadIntoClip = function(t, movie) {
+        var parent = t.parent, layer = t.layer;
+        var clip_actions = layer.clip_actions;
+        parent.clearLayer(layer);
+        if(movie)
+            parent.placeSprite(layer, movie.timeline, clip_actions, movie);
+        else
+            parent.placeSprite(layer, empty_sprite, clip_actions);
+        layer.updateUseHref();
+    };
+    // Run a sprite's `DoInitAction` code (at most once), see `showFrame`.
+    rt.initSprite = function(movie, id, root) {
+        var action = movie.init_actions[id];
+        if(action) {
+            movie.init_actions[id] = null;
+            action(rt.mkGlobalScope(), rt.mkLocalScope(rt.mkMovieClip(root)));
+        }
+    };
+    // Flash's key codes (as used by `Key`) are the same as the DOM's
+    // `keyCode`, except for a few keys which Firefox disagrees on.
+    var key_code_fixups = { 59: 186, 61: 187, 173: 189 };
+    rt.keyCode = function(ev) {
+        var code = ev.keyCode || 0;
+        return key_code_fixups[code] || code;
+    };
+    // `Key.getAscii` also covers a few keys which aren't printable.
+    var control_ascii = { Backspace: 8, Tab: 9, Enter: 13, Escape: 27, Esc: 27, Delete: 127, Del: 127 };
+    rt.keyAscii = function(ev) {
+        if(ev.key && ev.key.length == 1)
+            return ev.key.charCodeAt(0);
+        return control_ascii[ev.key] || 0;
+    };
+    // Button and clip `keyPress` events use ASCII for printable keys, and
+    // their own codes for some special keys (`Left` and `Esc` etc. being
+    // the older names some browsers use for `ev.key`).
+    var special_key_codes = {
+        ArrowLeft: 1, Left: 1,
+        ArrowRight: 2, Right: 2,
+        Home: 3,
+        End: 4,
+        Insert: 5,
+        Delete: 6, Del: 6,
+        Backspace: 8,
+        Enter: 13,
+        ArrowUp: 14, Up: 14,
+        ArrowDown: 15, Down: 15,
+        PageUp: 16,
+        PageDown: 17,
+        Tab: 18,
+        Escape: 19, Esc: 19,
+    };
+    rt.swfKeyCode = function(ev) {
+        if(special_key_codes.hasOwnProperty(ev.key))
+            return special_key_codes[ev.key];
+        if(ev.key && ev.key.length == 1 && ev.key.charCodeAt(0) < 128)
+            return ev.key.charCodeAt(0);
+        return null;
+    };
+    // Run the `onClipEvent` handlers attached to a sprite instance.
+    rt.clipEvent = function(timeline, event, key_code) {
+        var handlers = timeline.layer && timeline.layer.clip_actions;
+        if(!handlers)
+            return;
+        handlers.forEach(function(handler) {
+            if(handler.events.indexOf(event) == -1)
+                return;
+            if(event == 'keyPress' && handler.key_code !== key_code)
+                return;
+            handler.actions(rt.mkGlobalScope(), rt.mkLocalScope(rt.mkMovieClip(timeline)));
+        });
+    };
+    // Pointer events cover mouse, touch and pen input, where supported.
+    rt.pointer = window.PointerEvent ? {
+        down: 'pointerdown',
+        up: 'pointerup',
+        over: 'pointerover',
+        out: 'pointerout',
+    } : {
+        down: 'mousedown',
+        up: 'mouseup',
+        over: 'mouseover',
+        out: 'mouseout',
+    };
+    // Whether the mouse is down, anywhere (on the stage or not).
+    rt.mouse_pressed = false;
+    document.addEventListener(rt.pointer.down, function() {
+        rt.mouse_pressed = true;
+    }, true);
+    document.addEventListener(rt.pointer.up, function() {
+        rt.mouse_pressed = false;
+    }, true);
+
+    // Listen for the button-like clip events, which only
+    // apply to the clip itself (unlike e.g. `mouseDown`).
+    rt.attachClipListeners = function(timeline) {
+        var layer = timeline.layer;
+        var container = layer.container;
+        var pressed = false;
+        function outside(node) {
+            return !node || !container.contains(node);
+        }
+        var listeners = {};
+        listeners[rt.pointer.down] = function() {
+            pressed = true;
+            rt.clipEvent(timeline, 'press');
+        };
+        listeners[rt.pointer.up] = function() {
+            if(!pressed)
+                return;
+            pressed = false;
+            rt.clipEvent(timeline, 'release');
+        };
+        listeners[rt.pointer.over] = function(ev) {
+            if(outside(ev.relatedTarget))
+                rt.clipEvent(timeline, pressed ? 'dragOver' : 'rollOver');
+        };
+        listeners[rt.pointer.out] = function(ev) {
+            if(outside(ev.relatedTarget))
+                rt.clipEvent(timeline, pressed ? 'dragOut' : 'rollOut');
+        };
+        function releaseOutside(ev) {
+            if(!pressed || !outside(ev.target))
+                return;
+            pressed = false;
+            rt.clipEvent(timeline, 'releaseOutside');
+        }
+        for(var type in listeners)
+            container.addEventListener(type, listeners[type]);
+        document.addEventListener(rt.pointer.up, releaseOutside);
+        layer.detachClipListeners = function() {
+            for(var type in listeners)
+                container.removeEventListener(type, listeners[type]);
+            document.removeEventListener(rt.pointer.up, releaseOutside);
+            layer.detachClipListeners = null;
+        };
+    };
+
+    // Buttons react to the mouse only within their `hit_test` state, which
+    // gets its own (invisible) `<use>`, on top of the visible states, so
+    // that it receives all of the mouse events, instead of the latter.
+    rt.mkButton = function(timeline, layer, button_data) {
+        var hit = svg_element('use');
+        hit.setAttributeNS(
+            'http://www.w3.org/1999/xlink',
+            'href',
+            '#' + timeline.movie.id_prefix + 'c_' + layer.character + '_hit_test'
+        );
+        hit.setAttribute('opacity', 0);
+        hit.style.cursor = 'pointer';
+        // Touch input shouldn't scroll or zoom the page instead.
+        hit.style.touchAction = 'none';
+        layer.use.setAttribute('pointer-events', 'none');
+        layer.container.appendChild(hit);
+
+        var button = {
+            state: 'idle',
+            shown: 'up',
+            detach: function() {
+                for(var type in listeners)
+                    hit.removeEventListener(type, listeners[type]);
+                document.removeEventListener(rt.pointer.up, releaseOutside);
+                hit.remove();
+                layer.use.removeAttribute('pointer-events');
+            },
+            showFrame: function() {
+                // Catch the mouse having left without an event (e.g.
+                // because the button moved away from under it).
+                if(this.state == 'overUp' && !hit.matches(':hover'))
+                    this.transition('idle');
+            },
+            // Run the handler for a key (see `rt.swfKeyCode`), if any.
+            keyPress: function(key_code) {
+                var handler = button_data.keyPress[key_code];
+                if(layer.button !== this || !handler)
+                    return false;
+                handler(rt.mkGlobalScope(), rt.mkLocalScope(rt.mkMovieClip(timeline)));
+                return true;
+            },
+            transition: function(to) {
+                if(layer.button !== this)
+                    return;
+                var event = button_events[this.state][to];
+                if(!event)
+                    return;
+                this.state = to;
+                this.shown = button_shows[to];
+                layer.updateUseHref();
+                var sound = button_data.sounds[event];
+                if(sound)
+                    rt.playEventSound(timeline, sound);
+                var handler = button_data.mouse[event];
+                if(handler)
+                    handler(rt.mkGlobalScope(), rt.mkLocalScope(rt.mkMovieClip(timeline)));
+            },
+        };
+
+        var listeners = {};
+        listeners[rt.pointer.over] = function(ev) {
+            if(ev.relatedTarget === hit)
+                return;
+            if(!rt.mouse_pressed)
+                button.transition('overUp');
+            else if(button.state == 'outDown' || button_data.track_as_menu)
+                button.transition('overDown');
+        };
+        listeners[rt.pointer.out] = function(ev) {
+            if(ev.relatedTarget === hit)
+                return;
+            if(button.state == 'overUp')
+                button.transition('idle');
+            else if(button.state == 'overDown')
+                button.transition(button_data.track_as_menu ? 'idle' : 'outDown');
+        };
+        listeners[rt.pointer.down] = function(ev) {
+            // Touch input is captured by the element it started on,
+            // which would prevent the events for dragging out of it.
+            if(ev.pointerId !== undefined && hit.releasePointerCapture)
+                hit.releasePointerCapture(ev.pointerId);
+            button.transition('overUp');
+            button.transition('overDown');
+        };
+        listeners[rt.pointer.up] = function() {
+            button.transition('overUp');
+        };
+        function releaseOutside() {
+            if(button.state == 'outDown')
+                button.transition('idle');
+        }
+        for(var type in listeners)
+            hit.addEventListener(type, listeners[type]);
+        document.addEventListener(rt.pointer.up, releaseOutside);
+        return button;
+    };
+    // The graphics shown in each button state (see `rt.mkButton`).
+    var button_shows = {
+        idle: 'up',
+        overUp: 'over',
+        overDown: 'down',
+        // NB: Flash shows the "over" graphics while dragging out.
+        outDown: 'over',
+    };
+    // The events (in `buttons[x].mouse`) for each button state transition.
+    var button_events = {
+        idle: { overUp: 'hoverIn', overDown: 'downIn' },
+        overUp: { idle: 'hoverOut', overDown: 'down' },
+        overDown: { overUp: 'up', outDown: 'dragOut', idle: 'downOut' },
+        outDown: { overDown: 'dragIn', idle: 'upOut' },
+    };
+
+    // The timeline behind each movie clip object, see `rt.setMember`.
+    var clip_timelines = new WeakMap();
+    // The `SetMember` action, i.e. `object.name = value`. Movie clips are
+    // frozen, so their variables have to be written to the timeline.
+    rt.setMember = function(object, name, value) {
+        if(object === undefined || object === null)
+            return;
+        var timeline = clip_timelines.get(object);
+        if(!timeline) {
+            object[name] = value;
+            return;
+        }
+        name = '' + name;
+        if(rt.isProperty(name))
+            object[name] = value;
+        else
+            timeline.vars[name] = value;
+    };
+
     rt.mkMovieClip = function(timeline) {
-        var o = Object.create(null);
+        // Timeline variables are inherited, so that `name in clip` finds them.
+        var o = Object.create(timeline.vars);
         function def_get(name, f) {
             Object.defineProperty(o, name, { get: f });
         }
         function def(name, x) {
             Object.defineProperty(o, name, { value: x });
         }
+        Object.keys(rt.properties).forEach(function(name) {
+            var prop = rt.properties[name];
+            Object.defineProperty(o, name, {
+                get: prop.get.bind(null, timeline),
+                set: prop.set ? prop.set.bind(null, timeline) : function() {},
+            });
+        });
+        // HACK support for {Get,Set}Property.
+        def('getProperty', function(target, name) {
+            var t = rt.resolveTarget(timeline, target);
+            if(t)
+                return rt.mkMovieClip(t)[name];
+        });
+        def('setProperty', function(target, name, value) {
+            var t = rt.resolveTarget(timeline, target);
+            if(t)
+                rt.mkMovieClip(t)[name] = value;
+        });
         def('play', function() {
             timeline.paused = false;
         });
@@ -48,14 +1423,128 @@
         });
         // HACK(eddyb) support for Goto{Frame,Label}.
         def('goto', function(frame) {
-            if(typeof frame === 'string')
-                frame = timeline.labels[frame];
+            if(typeof frame === 'string') {
+                var label = frame;
+                frame = rt.labelFrame(timeline, label);
+                if(frame === undefined)
+                    return console.error('goto: unknown label', label);
+            }
+            if(!(frame >= 0 && frame < timeline.frame_count))
+                return console.error('goto: frame out of range', frame);
             timeline.frame = frame;
         });
-        def('gotoAndPlay', function(frame) {
+        // Scripts use 1-based frame numbers (or labels), optionally
+        // relative to a scene, e.g. `gotoAndPlay("Scene 2", 1)`.
+        function sceneFrame(args) {
+            var frame = args[args.length - 1];
+            var offset = 0;
+            if(args.length >= 2) {
+                var scene = timeline.scenes.filter(function(scene) {
+                    return scene.name == args[0];
+                })[0];
+                if(!scene)
+                    return console.error('unknown scene', args[0]);
+                offset = scene.offset;
+            }
+            if(typeof frame === 'string' && rt.labelFrame(timeline, frame) !== undefined)
+                return frame;
+            if(typeof frame === 'string' && (frame === '' || isNaN(frame)))
+                return console.error('unknown label', frame);
+            return int(frame) - 1 + offset;
+        }
+        def('gotoAndPlay', function() {
+            var frame = sceneFrame(arguments);
+            if(frame === undefined)
+                return;
             this.goto(frame);
             timeline.paused = false;
         });
+        def('gotoAndStop', function() {
+            var frame = sceneFrame(arguments);
+            if(frame === undefined)
+                return;
+            this.goto(frame);
+            timeline.paused = true;
+        });
+        // The index of the scene the current frame is in, or `-1`.
+        function currentScene() {
+            var frame = Math.max(timeline.renderedFrame, 0);
+            var i = timeline.scenes.length - 1;
+            while(i >= 0 && timeline.scenes[i].offset > frame)
+                i--;
+            return i;
+        }
+        def('nextScene', function() {
+            var i = currentScene();
+            if(i >= 0 && i + 1 < timeline.scenes.length)
+                this.goto(timeline.scenes[i + 1].offset);
+            timeline.paused = true;
+        });
+        def('prevScene', function() {
+            var i = currentScene();
+            if(i > 0)
+                this.goto(timeline.scenes[i - 1].offset);
+            timeline.paused = true;
+        });
+        def('nextFrame', function() {
+            this.goto(Math.min(Math.max(timeline.renderedFrame, 0) + 1, timeline.frame_count - 1));
+            timeline.paused = true;
+        });
+        def('prevFrame', function() {
+            this.goto(Math.max(timeline.renderedFrame - 1, 0));
+            timeline.paused = true;
+        });
+        // HACK support for GotoFrame2.
+        def('gotoFrame2', function(frame, play, scene_bias) {
+            var t = timeline;
+            if(typeof frame === 'string') {
+                // Labels and frame numbers can be prefixed by a target path.
+                var colon = frame.lastIndexOf(':');
+                if(colon != -1) {
+                    t = rt.resolveTarget(timeline, frame.slice(0, colon));
+                    frame = frame.slice(colon + 1);
+                }
+                if(!t)
+                    return;
+                if(rt.labelFrame(t, frame) === undefined && frame !== '' && !isNaN(frame))
+                    frame = +frame;
+            }
+            if(typeof frame === 'number')
+                frame = int(frame) - 1 + scene_bias;
+            var clip = rt.mkMovieClip(t);
+            clip.goto(frame);
+            if(play)
+                clip.play();
+            else
+                clip.stop();
+        });
+        def('tellTarget', function(target) {
+            var t = rt.resolveTarget(timeline, target);
+            if(!t) {
+                console.error('tellTarget: unknown target', target);
+                return o;
+            }
+            return rt.mkMovieClip(t);
+        });
+        def('stopAllSounds', function() {
+            forEachSound(function(sound) {
+                sound.userTimeline = null;
+                sound.pause();
+            });
+            rt.playing_sounds.slice().forEach(rt.stopSound);
+        });
+        // There is no real control over rendering quality, but
+        // SVG has a hint for trading off antialiasing for speed.
+        def('toggleHighQuality', function() {
+            var svg = timeline.root.container.ownerSVGElement;
+            if(svg.getAttribute('shape-rendering') == 'optimizeSpeed')
+                svg.removeAttribute('shape-rendering');
+            else
+                svg.setAttribute('shape-rendering', 'optimizeSpeed');
+        });
+        def('trace', function(x) {
+            console.log('' + x);
+        });
         // HACK(eddyb) these are usually only used as the
         // `getBytesLoaded() / getBytesTotal()` ratio.
         def('getBytesLoaded', function() {
@@ -64,79 +1553,381 @@
         def('getBytesTotal', function() {
             return 1;
         });
-        def('getURL', function(url, target) {
-            window.open(url, target);
+        def('getURL', function(url, target, method) {
+            if(/^_level\d+$/.test(target))
+                return this.loadMovieTarget(url, target, method);
+            rt.navigate(rt.mkRequest(url, method, method ? timeline.vars : null), target);
+        });
+        def('loadMovie', function(url, method) {
+            this.loadMovieTarget(url, this, method);
+        });
+        def('unloadMovie', function() {
+            this.loadMovieTarget('', this);
+        });
+        def('loadMovieNum', function(url, level, method) {
+            this.loadMovieTarget(url, '_level' + int(level), method);
+        });
+        def('unloadMovieNum', function(level) {
+            this.loadMovieTarget('', '_level' + int(level));
+        });
+        // HACK support for `GetUrl2` loading movies, where the
+        // target can also be a level that doesn't exist (yet).
+        def('loadMovieTarget', function(url, target, method) {
+            var level = /^_level(\d+)$/.exec(target);
+            var t = level ? rt.levels[+level[1]] : rt.resolveTarget(timeline, target);
+            if(!level && !t)
+                return console.error('loadMovie: unknown target', target);
+            if(t && !t.layer)
+                level = [, rt.levels.indexOf(t)];
+            if(!url) {
+                if(level)
+                    rt.unloadLevel(+level[1]);
+                else
+                    rt.loadIntoClip(t, null);
+                return;
+            }
+            var request = rt.mkRequest(url, method, method ? timeline.vars : null);
+            rt.fetchMovie(request, function(movie) {
+                if(level)
+                    rt.loadLevel(+level[1], movie);
+                else if(t.layer)
+                    rt.loadIntoClip(t, movie);
+            });
+        });
+        // Variables from this timeline are sent, and the response's
+        // variables are set on the target (which gets a `data` event).
+        def('loadVariables', function(url, target, method) {
+            var t = rt.resolveTarget(timeline, target);
+            if(!t)
+                return console.error('loadVariables: unknown target', target);
+            rt.loadVariables(rt.mkRequest(url, method, method ? timeline.vars : null), function(vars) {
+                for(var name in vars)
+                    t.vars[name] = vars[name];
+                rt.clipEvent(t, 'data');
+            });
+        });
+        def('loadVariablesNum', function(url, level, method) {
+            this.loadVariables(url, '_level' + int(level), method);
+        });
+        def('attachMovie', function(name, new_name, depth, init) {
+            var id = timeline.movie.export_names[name];
+            if(!timeline.movie.sprites[id])
+                return console.error('attachMovie: unknown sprite', name);
+            var layer = timeline.placeDynamicClip(depth, new_name, id, null, init);
+            return layer && rt.mkMovieClip(layer.sprite);
+        });
+        def('createEmptyMovieClip', function(name, depth) {
+            var layer = timeline.placeDynamicClip(depth, name, -1, null);
+            return layer && rt.mkMovieClip(layer.sprite);
+        });
+        def('duplicateMovieClip', function(name, depth, init) {
+            var old = timeline.layer;
+            if(!old)
+                return;
+            var layer = timeline.parent.placeDynamicClip(depth, name, old.character, old.clip_actions, init);
+            if(!layer)
+                return;
+            layer.matrix = old.matrix;
+            layer.color_transform = old.color_transform;
+            layer.filters = old.filters;
+            layer.visible = old.visible;
+            layer.cache_as_bitmap = old.cache_as_bitmap;
+            layer.background_color = old.background_color;
+            layer.applyTransform();
+            return rt.mkMovieClip(layer.sprite);
+        });
+        // Only movie clips at depths available to scripts can be removed.
+        def('removeMovieClip', function() {
+            var layer = timeline.layer;
+            if(layer && layer.depth >= DEPTH_OFFSET)
+                timeline.parent.removeLayer(layer.depth);
+        });
+        // HACK support for {Clone,Remove}Sprite, which take target paths.
+        // NB: the depth `CloneSprite` gets already has `DEPTH_OFFSET` added.
+        def('cloneSprite', function(target, name, depth) {
+            var t = rt.resolveTarget(timeline, target);
+            if(t)
+                rt.mkMovieClip(t).duplicateMovieClip(name, depth - DEPTH_OFFSET);
+        });
+        def('removeSprite', function(target) {
+            var t = rt.resolveTarget(timeline, target);
+            if(t)
+                rt.mkMovieClip(t).removeMovieClip();
+        });
+        def('getDepth', function() {
+            if(timeline.layer)
+                return timeline.layer.depth - DEPTH_OFFSET;
+        });
+        def('getNextHighestDepth', function() {
+            return Math.max(timeline.layers.length - DEPTH_OFFSET, 0);
+        });
+        // The target can be either a depth or a sibling movie clip.
+        def('swapDepths', function(target) {
+            var layer = timeline.layer;
+            if(!layer)
+                return;
+            var depth;
+            if(typeof target === 'number') {
+                depth = int(target) + DEPTH_OFFSET;
+            } else {
+                var t = rt.resolveTarget(timeline, target);
+                if(!t || t.parent !== timeline.parent)
+                    return;
+                depth = t.layer.depth;
+            }
+            timeline.parent.swapDepths(layer.depth, depth);
+        });
+        def('getVariable', function(path) {
+            var v = rt.resolveVariable(timeline, path);
+            if(!v.timeline)
+                return;
+            var clip = v.timeline === timeline ? o : rt.mkMovieClip(v.timeline);
+            if(v.name == 'this')
+                return clip;
+            // Bare `_root`, `_parent` and `_levelN` are targets, not variables.
+            if(/^(_root|_parent|_level\d+)$/.test(v.name)) {
+                var t = rt.resolveTarget(v.timeline, v.name);
+                return t ? rt.mkMovieClip(t) : undefined;
+            }
+            if(rt.hasVariable(v.timeline, v.name))
+                return clip[v.name];
+        });
+        // HACK support for GetVariable of plain names, falling back
+        // to `_global` (unlike variable paths, e.g. `_root.name`).
+        def('lookupVariable', function(name) {
+            if(rt.hasVariable(timeline, name))
+                return o[name];
+            return rt.mkGlobalScope()[name];
+        });
+        def('setVariable', function(path, value) {
+            var v = rt.resolveVariable(timeline, path);
+            if(!v.timeline)
+                return;
+            if(rt.isProperty(v.name))
+                rt.mkMovieClip(v.timeline)[v.name] = value;
+            else
+                v.timeline.vars[v.name] = value;
+        });
+        // HACK support for SetMember.
+        def('setMember', rt.setMember);
+        def('toString', function() {
+            var path = '';
+            for(var t = timeline; t.parent; t = t.parent)
+                path = '.' + rt.properties._name.get(t) + path;
+            return '_level' + Math.max(rt.levels.indexOf(t), 0) + path;
         });
         def_get('_root', rt.mkMovieClip.bind(null, timeline.root));
         if(timeline.parent)
             def_get('_parent', rt.mkMovieClip.bind(null, timeline.parent));
-        for(var name in timeline.named) {
-            var layer = timeline.layers[timeline.named[name]];
-            if(layer && layer.sprite)
-                def_get(name, rt.mkMovieClip.bind(null, layer.sprite));
-        }
+        // NB: children are looked up on every access, as scripts can remove them.
+        Object.keys(timeline.named).forEach(function(name) {
+            if(rt.child(timeline, name))
+                def_get(name, function() {
+                    var child = rt.child(timeline, name);
+                    return child && rt.mkMovieClip(child);
+                });
+        });
+        clip_timelines.set(o, timeline);
         // HACK(eddyb) trap writes.
         if(Object.freeze)
             return Object.freeze(o);
-        return api;
+        return o;
     };
 
-    function Timeline(data, container, id_prefix) {
+    // Layers are indexed by SWF depth, which is offset from the depths
+    // scripts see (e.g. `attachMovie(..., 0)` is at SWF depth 16384).
+    var DEPTH_OFFSET = 16384;
+
+    // Used by `createEmptyMovieClip`.
+    var empty_sprite = {
+        frame_count: 1,
+        layers: [],
+        clip_actions: [],
+        actions: [],
+        init_sprites: [],
+        labels: {},
+        sounds: [],
+        sound_stream: null,
+        scenes: [],
+    };
+
+    // Flash's blend modes (named as in `MovieClip#blendMode`) in CSS,
+    // with approximations for the ones CSS doesn't have.
+    var css_blend_modes = {
+        multiply: 'multiply',
+        screen: 'screen',
+        lighten: 'lighten',
+        darken: 'darken',
+        difference: 'difference',
+        overlay: 'overlay',
+        hardlight: 'hard-light',
+        add: 'plus-lighter',
+        subtract: 'difference',
+        invert: 'difference',
+    };
+    // Blend modes which make the layer a mask (see `attachLayers`).
+    var mask_blend_modes = { alpha: 'alpha', erase: 'erase' };
+
+    // Fills the bounding box of the element it's applied to with
+    // a (`flood-color`) background, while not clipping the element.
+    function mkBackgroundFilter(id) {
+        var filter = svg_element('filter');
+        filter.setAttribute('id', id);
+        filter.setAttribute('x', '-50%');
+        filter.setAttribute('y', '-50%');
+        filter.setAttribute('width', '200%');
+        filter.setAttribute('height', '200%');
+        filter.setAttribute('primitiveUnits', 'objectBoundingBox');
+        var flood = svg_element('feFlood');
+        flood.setAttribute('x', 0);
+        flood.setAttribute('y', 0);
+        flood.setAttribute('width', 1);
+        flood.setAttribute('height', 1);
+        flood.setAttribute('result', 'flood');
+        filter.appendChild(flood);
+        var merge = svg_element('feMerge');
+        ['flood', 'SourceGraphic'].forEach(function(input) {
+            var node = svg_element('feMergeNode');
+            node.setAttribute('in', input);
+            merge.appendChild(node);
+        });
+        filter.appendChild(merge);
+        return filter;
+    }
+
+    function mkLayer(movie, id_prefix, depth, frames) {
+        var container = svg_element('g');
+        var use = svg_element('use');
+        container.appendChild(use);
+
+        var filter = svg_element('filter');
+        id_prefix += 'd_' + depth + '_';
+        filter.setAttribute('id', id_prefix + 'filter');
+        filter.setAttribute('x', 0);
+        filter.setAttribute('y', 0);
+        filter.setAttribute('width', 1);
+        filter.setAttribute('height', 1);
+        var feColorMatrix = svg_element('feColorMatrix');
+        filter.appendChild(feColorMatrix);
+        container.appendChild(filter);
+
+        // Same nesting as the static export, from the inside out: the
+        // object's own transform, isolating blending inside the object,
+        // then filters, blending and visibility, all after the transform.
+        var transformed = svg_element('g');
+        transformed.appendChild(container);
+        var isolated = svg_element('g');
+        isolated.appendChild(transformed);
+        var outer = svg_element('g');
+        outer.appendChild(isolated);
+
+        return {
+            movie: movie,
+            depth: depth,
+            frames: frames,
+            id_prefix: id_prefix,
+            container: container,
+            transformed: transformed,
+            isolated: isolated,
+            outer: outer,
+            use: use,
+            filter: filter,
+            feColorMatrix: feColorMatrix,
+
+            ratio: null,
+            matrix: null,
+            color_transform: null,
+            visible: true,
+            cache_as_bitmap: false,
+            filters: null,
+            blend_mode: null,
+            background_color: null,
+            clip_depth: null,
+
+            // Masks are created when the layer first becomes one,
+            // see `Timeline.prototype.attachLayers`.
+            mask: null,
+            masked: null,
+            erase: null,
+
+            // Created when the layer first gets a background color.
+            background: null,
+
+            applyTransform: function() {
+                if(this.matrix) {
+                    this.transformed.setAttribute('transform', 'matrix(' + this.matrix.join(' ') + ')');
+                } else {
+                    this.transformed.removeAttribute('transform');
+                }
+                // The color transform applies before any other filters.
+                var filters = [];
+                if(this.color_transform) {
+                    this.feColorMatrix.setAttribute('values', this.color_transform.join(' '));
+                    filters.push('url(#' + this.filter.id + ')');
+                }
+                if(this.background_color) {
+                    if(!this.background) {
+                        this.background = mkBackgroundFilter(this.id_prefix + 'background');
+                        this.container.appendChild(this.background);
+                    }
+                    this.background.firstChild.setAttribute('flood-color', this.background_color);
+                    filters.push('url(#' + this.background.id + ')');
+                }
+                if(filters.length) {
+                    this.container.setAttribute('filter', filters.join(' '));
+                } else {
+                    this.container.removeAttribute('filter');
+                }
+                this.isolated.style.isolation = this.blend_mode == 'layer' ? 'isolate' : '';
+                if(this.filters) {
+                    this.outer.setAttribute('filter', 'url(#' + this.movie.id_prefix + this.filters + ')');
+                } else {
+                    this.outer.removeAttribute('filter');
+                }
+                this.outer.style.mixBlendMode = css_blend_modes[this.blend_mode] || '';
+                if(this.visible) {
+                    this.outer.removeAttribute('display');
+                } else {
+                    this.outer.setAttribute('display', 'none');
+                }
+            },
+
+            updateUseHref: function() {
+                if(this.character > 0) {
+                    var href = '#' + this.movie.id_prefix + 'c_' + this.character;
+                    if(this.button && this.button.shown != 'up')
+                        href += '_' + this.button.shown;
+                    if(href != this.useHref)
+                        this.use.setAttributeNS('http://www.w3.org/1999/xlink', 'href', href);
+                    this.useHref = href;
+                } else {
+                    this.use.removeAttributeNS('http://www.w3.org/1999/xlink', 'href');
+                    this.useHref = null;
+                }
+            }
+        };
+    }
+
+    function Timeline(movie, data, container, id_prefix) {
         if(!(this instanceof Timeline))
-            return new Timeline(data);
+            return new Timeline(movie, data);
 
         id_prefix = id_prefix || '';
         this.frame_count = data.frame_count;
         this.named = Object.create(null);
+        this.vars = Object.create(null);
         this.actions = data.actions;
+        this.clip_actions = data.clip_actions;
+        this.init_sprites = data.init_sprites;
         this.labels = data.labels;
+        this.scenes = data.scenes;
         this.sounds = data.sounds;
         this.sound_stream = data.sound_stream;
         this.activeSounds = [];
         this.layers = data.layers.map(function(frames, depth) {
-            var container = svg_element('g');
-            var use = svg_element('use');
-            container.appendChild(use);
-
-            var filter = svg_element('filter');
-            filter.setAttribute('id', id_prefix + 'd_' + depth + '_filter');
-            filter.setAttribute('x', 0);
-            filter.setAttribute('y', 0);
-            filter.setAttribute('width', 1);
-            filter.setAttribute('height', 1);
-            var feColorMatrix = svg_element('feColorMatrix');
-            filter.appendChild(feColorMatrix);
-            container.appendChild(filter);
-
-            return {
-                frames: frames,
-                container: container,
-                use: use,
-                filter: filter,
-                feColorMatrix: feColorMatrix,
-
-                ratio: null,
-
-                isHover: function() {
-                    // FIXME(eddyb) figure out how much this needs polyfill.
-                    return this.container.matches(':hover');
-                },
-
-                updateUseHref: function() {
-                    if(this.character > 0) {
-                        var href = '#c_' + this.character;
-                        if(this.button && this.button.state != 'up')
-                            href += '_' + this.button.state;
-                        if(href != this.useHref)
-                            this.use.setAttributeNS('http://www.w3.org/1999/xlink', 'href', href);
-                        this.useHref = href;
-                    } else {
-                        this.use.removeAttributeNS('http://www.w3.org/1999/xlink', 'href');
-                        this.useHref = null;
-                    }
-                }
-            };
+            return mkLayer(movie, id_prefix, depth, frames);
         });
+        this.movie = movie;
         this.root = this;
         this.container = container;
         this.id_prefix = id_prefix;
@@ -145,18 +1936,231 @@
     Timeline.prototype.paused = false;
     Timeline.prototype.frame = 0;
     Timeline.prototype.renderedFrame = -1;
+    Timeline.prototype.parent = null;
+    Timeline.prototype.layer = null;
+    Timeline.prototype.loaded = false;
+    Timeline.prototype.next_dynamic_id = 0;
+    Timeline.prototype.has_masks = false;
+    // Add all the layer containers, in order, to the timeline's container,
+    // with layers masked by another layer (see `clip_depth`) grouped
+    // together, and the mask layer itself moved into a `<mask>`.
+    // The `alpha` and `erase` blend modes also turn layers into masks,
+    // but for everything below them, instead of above.
     Timeline.prototype.attachLayers = function() {
-        var container = this.container;
+        var stack = [{ clip_depth: Infinity, element: this.container, nodes: [] }];
+        function add(node) {
+            var top = stack[stack.length - 1];
+            top.element.appendChild(node);
+            top.nodes.push(node);
+        }
         this.layers.forEach(function(layer) {
-            container.appendChild(layer.container);
+            while(layer.depth > stack[stack.length - 1].clip_depth)
+                stack.pop();
+            var mask_kind = layer.clip_depth ? 'clip' : mask_blend_modes[layer.blend_mode];
+            if(!mask_kind) {
+                if(layer.mask) {
+                    layer.mask.remove();
+                    layer.masked.remove();
+                }
+                add(layer.outer);
+                return;
+            }
+            if(!layer.mask) {
+                layer.mask = svg_element('mask');
+                layer.mask.setAttribute('id', layer.id_prefix + 'mask');
+                layer.masked = svg_element('g');
+                layer.masked.setAttribute('mask', 'url(#' + layer.id_prefix + 'mask)');
+            }
+            var mask = layer.mask;
+            mask.appendChild(layer.outer);
+            if(mask_kind == 'erase') {
+                // Turn the layer black, on top of white, to remove it from the mask.
+                if(!layer.erase) {
+                    var filter = svg_element('filter');
+                    filter.setAttribute('id', layer.id_prefix + 'erase');
+                    var feColorMatrix = svg_element('feColorMatrix');
+                    feColorMatrix.setAttribute('values', '0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 1 0');
+                    filter.appendChild(feColorMatrix);
+                    var rect = svg_element('rect');
+                    ['x', 'y'].forEach(function(a) { rect.setAttribute(a, -1000000); });
+                    ['width', 'height'].forEach(function(a) { rect.setAttribute(a, 2000000); });
+                    rect.setAttribute('fill', 'white');
+                    var g = svg_element('g');
+                    g.setAttribute('filter', 'url(#' + layer.id_prefix + 'erase)');
+                    layer.erase = [filter, rect, g];
+                }
+                layer.erase.forEach(function(node) {
+                    mask.appendChild(node);
+                });
+                layer.erase[2].appendChild(layer.outer);
+                mask.style.maskType = 'luminance';
+            } else {
+                if(layer.erase)
+                    layer.erase.forEach(function(node) {
+                        node.remove();
+                    });
+                mask.style.maskType = 'alpha';
+            }
+            if(mask_kind == 'clip') {
+                add(mask);
+                add(layer.masked);
+                stack.push({ clip_depth: layer.clip_depth, element: layer.masked, nodes: [] });
+            } else {
+                var top = stack[stack.length - 1];
+                top.nodes.forEach(function(node) {
+                    layer.masked.appendChild(node);
+                });
+                top.nodes = [];
+                add(mask);
+                add(layer.masked);
+            }
         });
     };
     Timeline.prototype.detachLayers = function() {
         this.layers.forEach(function(layer) {
-            layer.container.remove();
+            layer.outer.remove();
+            if(layer.mask) {
+                layer.mask.remove();
+                layer.masked.remove();
+            }
         });
     };
+    // NB: `movie` is only needed for sprites from another movie's library.
+    Timeline.prototype.placeSprite = function(layer, sprite_data, clip_actions, movie) {
+        movie = movie || this.movie;
+        layer.sprite = new Timeline(
+            movie,
+            sprite_data,
+            layer.container,
+            layer.id_prefix,
+        );
+        layer.sprite.parent = this;
+        layer.sprite.root = this.root;
+        layer.sprite.layer = layer;
+        layer.clip_actions = clip_actions;
+        if(layer.clip_actions) {
+            rt.clipEvent(layer.sprite, 'initialize');
+            rt.clipEvent(layer.sprite, 'construct');
+            rt.attachClipListeners(layer.sprite);
+        }
+    };
+    Timeline.prototype.clearLayer = function(layer) {
+        layer.character = -1;
+        layer.ratio = null;
+        if(layer.sprite) {
+            rt.clipEvent(layer.sprite, 'unload');
+            if(layer.detachClipListeners)
+                layer.detachClipListeners();
+            layer.clip_actions = null;
+            layer.sprite.detachLayers();
+            layer.sprite.parent = null;
+            layer.sprite.root = null;
+            layer.sprite.layer = null;
+            layer.sprite = null;
+        }
+        if(layer.button) {
+            layer.button.detach();
+            layer.button = null;
+        }
+    };
+    Timeline.prototype.setLayerName = function(layer, name) {
+        if(layer.name && this.named[layer.name] === layer.depth)
+            this.named[layer.name] = null;
+        layer.name = name;
+        if(name)
+            this.named[name] = layer.depth;
+    };
+    // Insert a layer before the next layer up.
+    Timeline.prototype.attachLayer = function(layer) {
+        // The grouping of masked layers may have to change.
+        if(this.has_masks)
+            return this.attachLayers();
+        var next = null;
+        this.layers.forEach(function(other) {
+            if(!next && other.depth > layer.depth)
+                next = other;
+        });
+        this.container.insertBefore(layer.outer, next && next.outer);
+    };
+    // Create an empty layer for scripts to place a movie clip in,
+    // replacing anything that might've been at that depth before.
+    Timeline.prototype.addDynamicLayer = function(depth) {
+        this.removeLayer(depth);
+        var layer = mkLayer(this.movie, this.id_prefix + 'n' + this.next_dynamic_id++ + '_', depth, []);
+        layer.dynamic = true;
+        this.layers[depth] = layer;
+        this.attachLayer(layer);
+        return layer;
+    };
+    // Place a movie clip at a depth (as seen by scripts), for `attachMovie`
+    // and the like, where `character` is -1 for an empty movie clip.
+    Timeline.prototype.placeDynamicClip = function(depth, name, character, clip_actions, init) {
+        depth = int(depth) + DEPTH_OFFSET;
+        if(depth < 0)
+            return console.error('placeDynamicClip: invalid depth', depth - DEPTH_OFFSET);
+        var layer = this.addDynamicLayer(depth);
+        layer.character = character;
+        this.placeSprite(layer, this.movie.sprites[character] || empty_sprite, clip_actions);
+        this.setLayerName(layer, '' + name);
+        layer.updateUseHref();
+        if(init) {
+            var clip = rt.mkMovieClip(layer.sprite);
+            Object.keys(init).forEach(function(key) {
+                if(rt.isProperty(key))
+                    clip[key] = init[key];
+                else
+                    layer.sprite.vars[key] = init[key];
+            });
+        }
+        return layer;
+    };
+    Timeline.prototype.removeLayer = function(depth) {
+        var layer = this.layers[depth];
+        if(!layer)
+            return;
+        this.clearLayer(layer);
+        this.setLayerName(layer, null);
+        layer.outer.remove();
+        delete this.layers[depth];
+        if(layer.mask) {
+            layer.mask.remove();
+            layer.masked.remove();
+            this.attachLayers();
+        }
+    };
+    // Move the contents of two layers (either of which can be empty),
+    // which also stops the timeline from controlling their movie clips.
+    Timeline.prototype.swapDepths = function(a, b) {
+        var layers = this.layers;
+        var old_a = layers[a], old_b = layers[b];
+        function moveTo(layer, depth, old) {
+            if(!layer) {
+                // Keep the timeline's placements for this depth around.
+                if(old && !old.dynamic)
+                    layers[depth] = mkLayer(old.movie, old.id_prefix + 's_', depth, old.frames);
+                else
+                    delete layers[depth];
+                return;
+            }
+            layer.depth = depth;
+            layer.dynamic = true;
+            layer.frames = [];
+            layers[depth] = layer;
+        }
+        moveTo(old_b, a, old_a);
+        moveTo(old_a, b, old_b);
+        [layers[a], layers[b]].forEach(function(layer) {
+            if(layer) {
+                if(layer.name)
+                    this.named[layer.name] = layer.depth;
+                this.attachLayer(layer);
+            }
+        }, this);
+    };
     Timeline.prototype.showFrame = function() {
+        if(this.loaded)
+            rt.clipEvent(this, 'enterFrame');
+
         if(this.paused && this.renderedFrame == this.frame) {
             // Update sprites and buttons even when paused.
             this.layers.forEach(function(layer) {
@@ -172,37 +2176,34 @@
         var frame = this.frame;
         var renderedFrame = this.renderedFrame;
         var named = this.named;
-        var id_prefix = this.id_prefix;
 
         if(renderedFrame > frame)
             renderedFrame = -1;
 
+        // `DoInitAction` code runs before anything else in its frame
+        // (including frames skipped over to get to this one).
+        for(var i = renderedFrame + 1; i <= frame; i++)
+            (this.init_sprites[i] || []).forEach(function(id) {
+                rt.initSprite(this.movie, id, this.root);
+            }, this);
+
+        var masksChanged = false;
         this.layers.forEach(function(layer, depth) {
             var obj, i;
             for(i = frame; i > renderedFrame && !obj && obj !== null; i--)
                 obj = layer.frames[i];
 
             // Fully remove anything not present yet.
-            var removeOld = renderedFrame == -1 || obj === null;
+            // Layers created by scripts aren't affected by the timeline.
+            var removeOld = !layer.dynamic && (renderedFrame == -1 || obj === null);
 
             // TODO(eddyb) this might need to take SWF's `is_move` into account.
             // HACK(eddyb) there's the issue of what `ratio` does, see also
             // http://wahlers.com.br/claus/blog/hacking-swf-2-placeobject-and-ratio/.
 
             // Remove the old character if necessary.
-            if(removeOld || (obj && (layer.character != obj.character || layer.ratio !== obj.ratio))) {
-                layer.character = -1;
-                layer.ratio = null;
-                if(layer.sprite) {
-                    layer.sprite.detachLayers();
-                    layer.sprite.parent = null;
-                    layer.sprite.root = null;
-                    layer.sprite = null;
-                }
-                if(layer.button) {
-                    layer.button = null;
-                }
-            }
+            if(removeOld || (obj && (layer.character != obj.character || layer.ratio !== obj.ratio)))
+                this.clearLayer(layer);
 
             // Remove the old name if necessary.
             if(layer.name && (removeOld || (obj && layer.name != obj.name))) {
@@ -210,83 +2211,48 @@
                 layer.name = null;
             }
 
+            // Stop masking if necessary.
+            if(layer.clip_depth && (removeOld || (obj && layer.clip_depth != obj.clip_depth))) {
+                layer.clip_depth = null;
+                masksChanged = true;
+            }
+            if(layer.blend_mode && (removeOld || (obj && layer.blend_mode != obj.blend_mode))) {
+                if(mask_blend_modes[layer.blend_mode])
+                    masksChanged = true;
+                layer.blend_mode = null;
+            }
+
             if(obj) {
                 if(layer.character != obj.character || layer.ratio !== obj.ratio) {
                     layer.character = obj.character;
                     layer.ratio = obj.ratio;
 
-                    var sprite_data = sprites[obj.character];
+                    var sprite_data = this.movie.sprites[obj.character];
                     if(sprite_data) {
-                        layer.sprite = new Timeline(
-                            sprite_data,
-                            layer.container,
-                            id_prefix + 'd_' + depth + '_',
-                        );
-                        layer.sprite.parent = this;
-                        layer.sprite.root = this.root;
-                    }
-                    var button_data = buttons[obj.character];
-                    if(button_data) {
-                        var button = layer.button = {
-                            state: 'up',
-                            attachListeners: function() {
-                                layer.use.addEventListener('mouseover', this.mouse_over_out_up);
-                                layer.use.addEventListener('mouseout', this.mouse_over_out_up);
-                                layer.use.addEventListener('mouseup', this.mouse_over_out_up);
-                                layer.use.addEventListener('mousedown', this.mouse_down);
-                            },
-                            detachListeners: function() {
-                                layer.use.removeEventListener('mouseover', this.mouse_over_out_up);
-                                layer.use.removeEventListener('mouseout', this.mouse_over_out_up);
-                                layer.use.removeEventListener('mouseup', this.mouse_over_out_up);
-                                layer.use.removeEventListener('mousedown', this.mouse_down);
-                            },
-                            mouse_over_out_up: function(ev) {
-                                button.transition(layer.isHover() ? 'over' : 'up');
-                            },
-                            mouse_down: function() {
-                                button.transition('down');
-                            },
-                            showFrame: function() {
-                                if(layer.button !== this)
-                                    return;
-                                if(!layer.isHover())
-                                    this.transition('up');
-                            },
-                            transition: function(to) {
-                                if(layer.button !== this)
-                                    return;
-                                if(this.state == to)
-                                    return;
-                                var event;
-                                if(this.state == 'up' && to == 'over') {
-                                    event = 'hoverIn';
-                                } else if(this.state == 'over' && to == 'up') {
-                                    event = 'hoverOut';
-                                } else if(this.state == 'over' && to == 'down') {
-                                    event = 'down';
-                                } else if(this.state == 'down' && to == 'over') {
-                                    event = 'up';
-                                }
-                                this.state = to;
-                                var handler = event && button_data.mouse[event];
-                                if(handler)
-                                    handler(rt.mkGlobalScope(), rt.mkLocalScope(mkMovieClip()));
-                            },
-                        };
-                        button.attachListeners();
+                        // Clip actions are exported once per timeline.
+                        var clip_actions = obj.clip_actions === null ? null : this.clip_actions[obj.clip_actions];
+                        this.placeSprite(layer, sprite_data, clip_actions);
                     }
+                    var button_data = this.movie.buttons[obj.character];
+                    if(button_data)
+                        layer.button = rt.mkButton(this, layer, button_data);
                 }
-                if(obj.matrix) {
-                    layer.container.setAttribute('transform', 'matrix(' + obj.matrix.join(' ') + ')');
-                } else {
-                    layer.container.removeAttribute('transform');
+                // Placing the object again overrides any scripted changes.
+                layer.matrix = obj.matrix;
+                layer.color_transform = obj.color_transform;
+                layer.filters = obj.filters;
+                layer.visible = obj.visible;
+                layer.cache_as_bitmap = obj.cache_as_bitmap;
+                layer.background_color = obj.background_color;
+                if(obj.blend_mode && layer.blend_mode != obj.blend_mode) {
+                    layer.blend_mode = obj.blend_mode;
+                    if(mask_blend_modes[layer.blend_mode])
+                        masksChanged = true;
                 }
-                if(obj.color_transform) {
-                    layer.feColorMatrix.setAttribute('values', obj.color_transform.join(' '));
-                    layer.container.setAttribute('filter', 'url(#' + layer.filter.id + ')');
-                } else {
-                    layer.container.removeAttribute('filter');
+                layer.applyTransform();
+                if(obj.clip_depth && layer.clip_depth != obj.clip_depth) {
+                    layer.clip_depth = obj.clip_depth;
+                    masksChanged = true;
                 }
                 if(layer.name != obj.name) {
                     layer.name = obj.name;
@@ -303,7 +2269,12 @@
 
             // Update the <use> element.
             layer.updateUseHref();
-        });
+        }, this);
+
+        if(masksChanged) {
+            this.has_masks = true;
+            this.attachLayers();
+        }
 
         if(renderedFrame == -1) {
             var activeSounds = this.activeSounds;
@@ -327,6 +2298,7 @@
             this.activeSounds = [];
         }
 
+        var sounds = this.movie.sounds;
         for(var i = renderedFrame + 1; i <= frame; i++) {
             var timeline = this;
 
@@ -351,6 +2323,7 @@
                     // (currently calling `showFrame` in a loop doesn't do the right thing).
                     sound.currentTime = (frame - i) / frame_rate;
                 }
+                sound.volume = rt.soundVolume(timeline);
                 var promise = sound.play();
                 if(promise && promise.catch)
                     promise.catch(function(e) {
@@ -375,12 +2348,70 @@
         if(action)
             action(rt.mkGlobalScope(), rt.mkLocalScope(mkMovieClip()));
 
+        if(!this.loaded) {
+            this.loaded = true;
+            rt.clipEvent(this, 'load');
+        }
+
         // HACK(eddyb) no idea what the interaction here should be.
-        if(!this.paused)
+        // Don't advance past a frame that the actions jumped to.
+        if(!this.paused && this.frame === frame)
             this.frame = (frame + 1) % this.frame_count;
     };
 
-    timeline = new Timeline(timeline, document.getElementById('body'));
+    rt.stage = document.getElementById('body');
+    var timeline = rt.levels[0] = new Timeline(main_movie, main_movie.timeline, rt.stage);
+
+    // Stage-wide mouse and keyboard clip events.
+    [
+        ['mousedown', 'mouseDown'],
+        ['mouseup', 'mouseUp'],
+        ['mousemove', 'mouseMove'],
+        ['keydown', 'keyDown'],
+        ['keyup', 'keyUp'],
+    ].forEach(function(pair) {
+        document.addEventListener(pair[0], function(ev) {
+            if(pair[0] == 'keydown' || pair[0] == 'keyup') {
+                var down = pair[0] == 'keydown';
+                var code = rt.keyCode(ev);
+                if(down)
+                    rt.keys_down[code] = true;
+                else
+                    delete rt.keys_down[code];
+                rt.last_key = {
+                    code: code,
+                    ascii: rt.keyAscii(ev),
+                };
+                if(ev.getModifierState)
+                    rt.caps_lock = ev.getModifierState('CapsLock');
+            }
+            rt.forEachTimeline(function(t) {
+                rt.clipEvent(t, pair[1]);
+            });
+            var listeners = pair[0].lastIndexOf('key', 0) == 0 ? rt.key_listeners : rt.mouse_listeners;
+            rt.broadcast(listeners, 'on' + pair[1][0].toUpperCase() + pair[1].slice(1));
+            if(pair[0] == 'keydown') {
+                var key_code = rt.swfKeyCode(ev);
+                if(key_code === null)
+                    return;
+                var handled = false;
+                rt.forEachTimeline(function(t) {
+                    rt.clipEvent(t, 'keyPress', key_code);
+                    t.layers.forEach(function(layer) {
+                        if(layer.button && layer.button.keyPress(key_code))
+                            handled = true;
+                    });
+                });
+                // Keep e.g. arrow keys from also scrolling the page.
+                if(handled)
+                    ev.preventDefault();
+            }
+        });
+    });
+    // Keys released while the page isn't focused would otherwise stay down.
+    window.addEventListener('blur', function() {
+        rt.keys_down = Object.create(null);
+    });
 
     var start;
     var last_frame = 0;
@@ -392,18 +2423,22 @@
         var frame = int((now - start) * frame_rate / 1000);
 
         for(; last_frame < frame; last_frame++)
-            timeline.showFrame();
+            rt.levels.forEach(function(level) {
+                level.showFrame();
+            });
     }
 
     // HACK(eddyb) work around unreasonable autoplay policies in Chrome.
     // See https://goo.gl/xX8pDD for their one-sided description of it.
     var anySounds = false;
     function forEachSound(f) {
-        timeline.sound_stream && f(timeline.sound_stream.sound);
-        sprites.forEach(function(sprite) {
-            sprite.sound_stream && f(sprite.sound_stream.sound);
+        rt.movies.forEach(function(movie) {
+            movie.timeline.sound_stream && f(movie.timeline.sound_stream.sound);
+            movie.sprites.forEach(function(sprite) {
+                sprite.sound_stream && f(sprite.sound_stream.sound);
+            });
+            movie.sounds.forEach(f);
         });
-        sounds.forEach(f);
     }
     forEachSound(function() { anySounds = true; })
     if(anySounds) {
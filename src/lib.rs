@@ -1,6 +1,8 @@
 #![forbid(unsafe_code)]
 
+pub mod assets;
 pub mod avm1;
+pub mod avm2;
 pub mod bitmap;
 pub mod button;
 pub mod dictionary;
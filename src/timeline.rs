@@ -3,6 +3,7 @@ use crate::dictionary::CharacterId;
 use crate::sound;
 use std::collections::BTreeMap;
 use std::ops::Add;
+use std::rc::Rc;
 use std::str;
 use swf_types as swf;
 
@@ -19,13 +20,105 @@ impl Add for Frame {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ClipEvent {
+    // Lifecycle events.
+    Initialize,
+    Construct,
+    Load,
+    Unload,
+    EnterFrame,
+    Data,
+
+    // Keyboard events.
+    KeyDown,
+    KeyUp,
+    KeyPress(u8),
+
+    // Mouse events (anywhere on the stage).
+    MouseDown,
+    MouseUp,
+    MouseMove,
+
+    // Button-like mouse events (on the clip itself).
+    Press,
+    Release,
+    ReleaseOutside,
+    RollOver,
+    RollOut,
+    DragOver,
+    DragOut,
+}
+
+#[derive(Debug)]
+pub struct ClipEventHandler {
+    pub on: Vec<ClipEvent>,
+    pub actions: avm1::Code,
+}
+
+impl From<&swf::ClipAction> for ClipEventHandler {
+    fn from(clip_action: &swf::ClipAction) -> Self {
+        let events = &clip_action.events;
+        let on = [
+            (ClipEvent::Initialize, events.initialize),
+            (ClipEvent::Construct, events.construct),
+            (ClipEvent::Load, events.load),
+            (ClipEvent::Unload, events.unload),
+            (ClipEvent::EnterFrame, events.enter_frame),
+            (ClipEvent::Data, events.data),
+            (ClipEvent::KeyDown, events.key_down),
+            (ClipEvent::KeyUp, events.key_up),
+            (ClipEvent::MouseDown, events.mouse_down),
+            (ClipEvent::MouseUp, events.mouse_up),
+            (ClipEvent::MouseMove, events.mouse_move),
+            (ClipEvent::Press, events.press),
+            (ClipEvent::Release, events.release),
+            (ClipEvent::ReleaseOutside, events.release_outside),
+            (ClipEvent::RollOver, events.roll_over),
+            (ClipEvent::RollOut, events.roll_out),
+            (ClipEvent::DragOver, events.drag_over),
+            (ClipEvent::DragOut, events.drag_out),
+        ]
+        .iter()
+        .filter(|&&(_, on)| on)
+        .map(|&(ev, _)| ev)
+        .chain(
+            clip_action
+                .key_code
+                .filter(|_| events.key_press)
+                .map(ClipEvent::KeyPress),
+        )
+        .collect();
+
+        let actions = avm1::Code::parse_and_compile(&clip_action.actions);
+
+        ClipEventHandler { on, actions }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Object<'a> {
     pub character: CharacterId,
     pub matrix: swf::Matrix,
     pub name: Option<&'a str>,
     pub color_transform: swf::ColorTransformWithAlpha,
     pub ratio: Option<u16>,
+    pub clip_actions: Option<Rc<Vec<ClipEventHandler>>>,
+    pub filters: Vec<swf::Filter>,
+    pub blend_mode: swf::BlendMode,
+    pub visible: bool,
+
+    // Only exposed to scripts (as `cacheAsBitmap`), the object is
+    // always rendered as vectors, with no caching.
+    pub cache_as_bitmap: bool,
+
+    // If present, the object's bounds are filled with this color (ignoring
+    // its alpha) behind it, like `opaqueBackground` in ActionScript.
+    pub background_color: Option<swf::StraightSRgba8>,
+
+    // If present, this object is a mask for all the objects above it,
+    // up to (and including) the ones at `clip_depth`, and isn't itself shown.
+    pub clip_depth: Option<Depth>,
 }
 
 impl<'a> Object<'a> {
@@ -36,16 +129,23 @@ impl<'a> Object<'a> {
             name: None,
             color_transform: swf::ColorTransformWithAlpha::default(),
             ratio: None,
+            clip_actions: None,
+            filters: vec![],
+            blend_mode: swf::BlendMode::Normal,
+            visible: true,
+            cache_as_bitmap: false,
+            background_color: None,
+            clip_depth: None,
         }
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Clone, Default, Debug)]
 pub struct Layer<'a> {
     pub frames: BTreeMap<Frame, Option<Object<'a>>>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SoundStream {
     pub start: Frame,
     pub format: swf::AudioCodingFormat,
@@ -53,14 +153,127 @@ pub struct SoundStream {
     pub mp3: Vec<u8>,
 }
 
-#[derive(Default, Debug)]
+#[derive(Clone, Default, Debug)]
 pub struct Timeline<'a> {
     pub layers: BTreeMap<Depth, Layer<'a>>,
     pub actions: BTreeMap<Frame, Vec<avm1::Code>>,
-    pub labels: BTreeMap<&'a str, Frame>,
+    // Sprites whose `DoInitAction` code runs in each frame, before that
+    // frame's `actions` (only on the main timeline).
+    pub init_sprites: BTreeMap<Frame, Vec<CharacterId>>,
+    // NB: frame labels are case-insensitive, so they're kept lowercase.
+    pub labels: BTreeMap<String, Frame>,
     pub sounds: BTreeMap<Frame, Vec<&'a swf::tags::StartSound>>,
     pub sound_stream: Option<SoundStream>,
     pub frame_count: Frame,
+
+    // Scene names, by the frame each scene starts at (only on the main
+    // timeline, and only in movies with `DefineSceneAndFrameLabelData`).
+    pub scenes: BTreeMap<Frame, &'a str>,
+}
+
+impl<'a> Timeline<'a> {
+    // Extract the scene starting at `start` (see `scenes`),
+    // into a timeline of its own, as if it were the whole movie.
+    pub fn into_scene(self, start: Frame) -> Timeline<'a> {
+        let end = self
+            .scenes
+            .range(start + Frame(1)..)
+            .next()
+            .map_or(self.frame_count, |(&frame, _)| frame);
+        let in_scene = |frame: Frame| start <= frame && frame < end;
+        let rebase = |frame: Frame| Frame(frame.0 - start.0);
+
+        let layers = self
+            .layers
+            .into_iter()
+            .filter_map(|(depth, layer)| {
+                // Objects placed before the scene are still there at its start.
+                let mut before = None;
+                let mut frames = BTreeMap::new();
+                for (frame, obj) in layer.frames {
+                    if frame < start {
+                        before = obj;
+                    } else if in_scene(frame) {
+                        frames.insert(rebase(frame), obj);
+                    }
+                }
+                if let Some(obj) = before {
+                    frames.entry(Frame(0)).or_insert(Some(obj));
+                }
+                if frames.is_empty() {
+                    None
+                } else {
+                    Some((depth, Layer { frames }))
+                }
+            })
+            .collect();
+
+        let mut actions: BTreeMap<_, _> = self
+            .actions
+            .into_iter()
+            .filter(|&(frame, _)| in_scene(frame))
+            .map(|(frame, codes)| (rebase(frame), codes))
+            .collect();
+        for (frame, codes) in &mut actions {
+            for code in codes {
+                for target in code.rebase_frames(start, end) {
+                    eprintln!(
+                        "Timeline::into_scene: frame {} actions go to frame {}, outside the scene, ignoring",
+                        frame.0, target.0,
+                    );
+                }
+            }
+        }
+
+        // `DoInitAction` code from before the scene still has to run.
+        let mut init_sprites = BTreeMap::new();
+        for (frame, sprites) in self.init_sprites {
+            if frame < start {
+                init_sprites
+                    .entry(Frame(0))
+                    .or_insert_with(Vec::new)
+                    .extend(sprites);
+            } else if in_scene(frame) {
+                init_sprites
+                    .entry(rebase(frame))
+                    .or_insert_with(Vec::new)
+                    .extend(sprites);
+            }
+        }
+
+        Timeline {
+            layers,
+            actions,
+            init_sprites,
+            labels: self
+                .labels
+                .into_iter()
+                .filter(|&(_, frame)| in_scene(frame))
+                .map(|(name, frame)| (name, rebase(frame)))
+                .collect(),
+            sounds: self
+                .sounds
+                .into_iter()
+                .filter(|&(frame, _)| in_scene(frame))
+                .map(|(frame, sounds)| (rebase(frame), sounds))
+                .collect(),
+            // FIXME cut the stream short at the end of the scene.
+            sound_stream: self
+                .sound_stream
+                .filter(|stream| in_scene(stream.start))
+                .map(|stream| SoundStream {
+                    start: rebase(stream.start),
+                    ..stream
+                }),
+            frame_count: Frame(end.0 - start.0),
+            scenes: self
+                .scenes
+                .get(&start)
+                .map(|&name| (Frame(0), name))
+                .into_iter()
+                .collect(),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -76,7 +289,7 @@ impl<'a> TimelineBuilder<'a> {
         // Find the last changed frame for this object, if it's not
         // the current one, and copy its state of the object.
         let prev_obj = match layer.frames.range(..=self.current_frame).rev().next() {
-            Some((&frame, &obj)) if frame != self.current_frame => obj,
+            Some((&frame, obj)) if frame != self.current_frame => obj.clone(),
             _ => None,
         };
 
@@ -112,15 +325,31 @@ impl<'a> TimelineBuilder<'a> {
         if let Some(ratio) = place.ratio {
             obj.ratio = Some(ratio);
         }
+        if let Some(filters) = &place.filters {
+            obj.filters = filters.clone();
+        }
+        if let Some(blend_mode) = place.blend_mode {
+            obj.blend_mode = blend_mode;
+        }
+        if let Some(visible) = place.visible {
+            obj.visible = visible;
+        }
+        if let Some(bitmap_cache) = place.bitmap_cache {
+            obj.cache_as_bitmap = bitmap_cache;
+        }
+        if let Some(background_color) = place.background_color {
+            obj.background_color = Some(background_color);
+        }
+        if let Some(clip_depth) = place.clip_depth {
+            obj.clip_depth = Some(Depth(clip_depth));
+        }
+        if let Some(clip_actions) = &place.clip_actions {
+            obj.clip_actions = Some(Rc::new(
+                clip_actions.iter().map(ClipEventHandler::from).collect(),
+            ));
+        }
 
-        if place.class_name.is_some()
-            || place.clip_depth.is_some()
-            || place.filters.is_some()
-            || place.blend_mode.is_some()
-            || place.visible.is_some()
-            || place.background_color.is_some()
-            || place.clip_actions.is_some()
-        {
+        if place.class_name.is_some() {
             eprintln!(
                 "TimelineBuilder::place_object: unsupported features in {:?}",
                 place
@@ -145,8 +374,34 @@ impl<'a> TimelineBuilder<'a> {
             .push(avm1::Code::parse_and_compile(&do_action.actions))
     }
 
+    pub fn do_init_action(&mut self, init: &swf::tags::DoInitAction) {
+        self.timeline
+            .init_sprites
+            .entry(self.current_frame)
+            .or_default()
+            .push(CharacterId(init.sprite_id));
+    }
+
     pub fn frame_label(&mut self, label: &'a swf::tags::FrameLabel) {
-        self.timeline.labels.insert(&label.name, self.current_frame);
+        self.timeline
+            .labels
+            .insert(label.name.to_lowercase(), self.current_frame);
+    }
+
+    pub fn scene_and_frame_label_data(
+        &mut self,
+        data: &'a swf::tags::DefineSceneAndFrameLabelData,
+    ) {
+        for scene in &data.scenes {
+            self.timeline
+                .scenes
+                .insert(Frame(scene.offset as u16), &scene.name);
+        }
+        for label in &data.labels {
+            self.timeline
+                .labels
+                .insert(label.name.to_lowercase(), Frame(label.frame as u16));
+        }
     }
 
     pub fn start_sound(&mut self, sound: &'a swf::tags::StartSound) {
@@ -214,6 +469,19 @@ impl<'a> TimelineBuilder<'a> {
         }
         self.timeline.frame_count = frame_count;
 
+        // Labels can be defined after the actions using them,
+        // so they can only be resolved once the timeline is complete.
+        for (frame, codes) in &mut self.timeline.actions {
+            for code in codes {
+                for label in code.resolve_labels(&self.timeline.labels) {
+                    eprintln!(
+                        "TimelineBuilder::finish: unknown label {:?} in frame {} actions",
+                        label, frame.0,
+                    );
+                }
+            }
+        }
+
         self.timeline
     }
 }
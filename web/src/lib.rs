@@ -1,18 +1,95 @@
 use futures::Future;
-use js_sys::{Promise, Uint8Array};
+use js_sys::{Function, Object, Promise, Reflect, Uint8Array};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::{future_to_promise, JsFuture};
 use web_sys::{console, Element, Request, RequestInit, RequestMode, Response, SvgScriptElement};
 
-fn convert_swf(swf: &[u8]) -> String {
+fn convert_swf(swf: &[u8]) -> Result<String, String> {
     match swf_parser::parse_swf(&swf) {
-        Ok(movie) => {
-            flashback::export::svg::export(&movie, flashback::export::svg::Config { use_js: true })
-                .to_string()
-        }
-        Err(e) => format!("swf-parser errored: {:?}", e),
+        Ok(movie) => Ok(flashback::export::svg::export(
+            &movie,
+            flashback::export::svg::Config {
+                use_js: true,
+                ..Default::default()
+            },
+        )
+        .to_string()),
+        Err(e) => Err(format!("swf-parser errored: {:?}", e)),
+    }
+}
+
+fn fetch(url: &str) -> impl Future<Item = Vec<u8>, Error = JsValue> {
+    let mut opts = RequestInit::new();
+    opts.mode(RequestMode::Cors);
+
+    let request = Request::new_with_str_and_init(
+        &format!("https://cors-anywhere.herokuapp.com/{}", url),
+        &opts,
+    )
+    .unwrap();
+
+    let window = web_sys::window().unwrap();
+    JsFuture::from(window.fetch_with_request(&request))
+        .and_then(|resp_value| {
+            assert!(resp_value.is_instance_of::<Response>());
+            let resp: Response = resp_value.dyn_into().unwrap();
+            resp.array_buffer()
+        })
+        .and_then(|buffer_value: Promise| JsFuture::from(buffer_value))
+        .map(|buffer_value| {
+            let buffer = Uint8Array::new(&buffer_value);
+            let mut data = vec![0; buffer.length() as usize];
+            buffer.copy_to(&mut data);
+            data
+        })
+}
+
+// Resolve `url` relative to `base`, both being scheme-less (`foo.com/bar`).
+fn resolve_url(base: &str, url: &str) -> String {
+    if let Some(i) = url.find("://") {
+        return url[i + 3..].to_string();
     }
+    if url.starts_with('/') {
+        let host = base.split('/').next().unwrap();
+        return format!("{}{}", host, url);
+    }
+    let dir = base.rfind('/').map_or(base, |i| &base[..i]);
+    format!("{}/{}", dir, url)
+}
+
+// Let the runtime load other movies (via `loadMovie`), by fetching and
+// converting them the same way the main movie is.
+fn install_host(base_url: String) {
+    let load_movie = Closure::wrap(Box::new(move |request: JsValue, callback: Function| {
+        let url = Reflect::get(&request, &"url".into())
+            .ok()
+            .and_then(|url| url.as_string())
+            .unwrap_or_default();
+        let url = resolve_url(&base_url, &url);
+        let callback_err = callback.clone();
+        // NB: failures (including `callback` throwing) go to `callback_err`,
+        // and only errors thrown by that are left, so nothing is unhandled.
+        let _ = future_to_promise(
+            fetch(&url)
+                .and_then(move |data| match convert_swf(&data) {
+                    Ok(svg) => callback.call2(&JsValue::null(), &JsValue::null(), &svg.into()),
+                    Err(e) => callback.call1(&JsValue::null(), &e.into()),
+                })
+                .or_else(move |e| callback_err.call1(&JsValue::null(), &e))
+                .or_else(|e| {
+                    console::error_2(&"loadMovie: callback failed:".into(), &e);
+                    Ok(JsValue::undefined())
+                }),
+        );
+    }) as Box<dyn FnMut(JsValue, Function)>);
+
+    let host = Object::new();
+    Reflect::set(&host, &"loadMovie".into(), load_movie.as_ref()).unwrap();
+    // FIXME memory management?
+    load_movie.forget();
+    let window = web_sys::window().unwrap();
+    Reflect::set(&window, &"flashback".into(), &host).unwrap();
 }
 
 fn request_animation_frame(f: impl FnOnce() + 'static) {
@@ -29,51 +106,36 @@ fn request_animation_frame(f: impl FnOnce() + 'static) {
 fn load_swf_from_url(container: Element, url: String) {
     container.set_inner_html(&format!("Downloading `{}`...", url));
 
-    let mut opts = RequestInit::new();
-    opts.mode(RequestMode::Cors);
-
-    let request = Request::new_with_str_and_init(
-        &format!("https://cors-anywhere.herokuapp.com/{}", url),
-        &opts,
-    )
-    .unwrap();
+    future_to_promise(fetch(&url).map(move |data| {
+        container.set_inner_html(&format!(
+            "Converting `{}` ({:.2}kB) to SVG...",
+            url,
+            data.len() as f64 / 1000.0,
+        ));
 
-    let window = web_sys::window().unwrap();
-    future_to_promise(
-        JsFuture::from(window.fetch_with_request(&request))
-            .and_then(|resp_value| {
-                assert!(resp_value.is_instance_of::<Response>());
-                let resp: Response = resp_value.dyn_into().unwrap();
-                resp.array_buffer()
+        request_animation_frame(move || {
+            request_animation_frame(move || {
+                let svg = match convert_swf(&data) {
+                    Ok(svg) => svg,
+                    Err(e) => return container.set_inner_html(&e),
+                };
+                container.set_inner_html(&svg);
+                install_host(url);
+
+                // HACK(eddyb) manually evaluate script sources (in order, as
+                // the runtime depends on the movie data defined before it).
+                let scripts = container.query_selector_all("script").unwrap();
+                for i in 0..scripts.length() {
+                    let script = scripts.get(i).unwrap();
+                    if let Ok(script) = script.dyn_into::<SvgScriptElement>() {
+                        js_sys::eval(&script.text_content().unwrap()).unwrap();
+                    }
+                }
             })
-            .and_then(|buffer_value: Promise| JsFuture::from(buffer_value))
-            .map(move |buffer_value| {
-                let buffer = Uint8Array::new(&buffer_value);
-                let mut data = vec![0; buffer.length() as usize];
-                buffer.copy_to(&mut data);
-
-                container.set_inner_html(&format!(
-                    "Converting `{}` ({:.2}kB) to SVG...",
-                    url,
-                    buffer.length() as f64 / 1000.0,
-                ));
-
-                request_animation_frame(move || {
-                    request_animation_frame(move || {
-                        container.set_inner_html(&convert_swf(&data));
-
-                        // HACK(eddyb) manually evaluate script sources;
-                        if let Some(script) = container.query_selector("script").unwrap() {
-                            if let Ok(script) = script.dyn_into::<SvgScriptElement>() {
-                                js_sys::eval(&script.text_content().unwrap()).unwrap();
-                            }
-                        }
-                    })
-                });
-
-                JsValue::undefined()
-            }),
-    );
+        });
+
+        JsValue::undefined()
+    }));
 }
 
 fn load_swf_from_hash(container: Element) {